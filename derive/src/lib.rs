@@ -42,7 +42,7 @@ fn from_repl_struct(data: DataStruct) -> TokenStream {
             for f in fields.unnamed {
                 let ty = f.ty;
                 lines.push(quote! {
-                    #ty::from_repl(&mut words.take(1))?,
+                    <#ty as FromRepl>::from_repl(words)?,
                 });
             }
             quote! {
@@ -65,6 +65,13 @@ fn from_repl_enum(data: DataEnum) -> TokenStream {
         let variant_name = variant.ident;
 
         //TODO find some way to not force the existence of a variant named "Unknown"
+        if variant_name == "Unknown" && holds_u8(&variant.fields) {
+            // a value that isn't one of the named ones
+            match_arms.push(quote! {
+                _ => Self::#variant_name(<u8 as std::str::FromStr>::from_str(word)?),
+            });
+            continue;
+        }
         if variant_name == "Unknown" {
             match_arms.push(quote! {
                 _ => {
@@ -76,10 +83,10 @@ fn from_repl_enum(data: DataEnum) -> TokenStream {
             continue;
         }
 
-        // if discriminant, then no field
-        if let Some(_) = variant.discriminant {
+        // if discriminant or unit, then no field
+        if variant.discriminant.is_some() || matches!(variant.fields, Fields::Unit) {
             match_arms.push(quote! {
-                stringify!(#variant_name) => Self::#variant_name,
+                w if w.eq_ignore_ascii_case(stringify!(#variant_name)) => Self::#variant_name,
             });
             continue;
         }
@@ -91,14 +98,16 @@ fn from_repl_enum(data: DataEnum) -> TokenStream {
             _ => unimplemented!(),
         }
         .unnamed;
-        let field = match fields.into_iter().nth(0) {
+        let field = match fields.into_iter().next() {
             Some(f) => f,
             None => unimplemented!(),
         };
         let ty = field.ty;
 
         match_arms.push(quote! {
-            stringify!(#variant_name) => Self::#variant_name(#ty::from_repl(words)?),
+            w if w.eq_ignore_ascii_case(stringify!(#variant_name)) => {
+                Self::#variant_name(<#ty as FromRepl>::from_repl(words)?)
+            }
         });
     }
 
@@ -117,6 +126,19 @@ fn from_repl_enum(data: DataEnum) -> TokenStream {
     }
 }
 
+/// Whether `fields` is a single `u8`
+fn holds_u8(fields: &Fields) -> bool {
+    match fields {
+        Fields::Unnamed(f) => {
+            f.unnamed.len() == 1 && {
+                let ty = &f.unnamed[0].ty;
+                quote!(#ty).to_string() == "u8"
+            }
+        }
+        _ => false,
+    }
+}
+
 fn repl_completion(input: DeriveInput) -> TokenStream {
     let type_name = input.ident;
 
@@ -147,27 +169,21 @@ fn repl_completion_struct(data: DataStruct) -> TokenStream {
             for (i, f) in fields.unnamed.into_iter().enumerate() {
                 let field_type = f.ty;
                 let current_sub_tree_fn = format_ident!("sub_tree_{}", i);
-                let next_sub_tree_fn = if i == num_fields - 1 {
-                    quote! {
-                        crate::repl::CompletionTree::lazy_empty()
-                    }
+                let current_sub_tree = quote! {
+                    <#field_type as crate::repl::ReplCompletion>::completion_tree()
+                };
+                let sub_tree = if i == num_fields - 1 {
+                    current_sub_tree
                 } else {
                     let next_ident = format_ident!("sub_tree_{}", i + 1);
                     quote! {
-                        Box::new(#next_ident)
+                        #current_sub_tree.then(#next_ident)
                     }
                 };
 
                 sub_tree_fns.push(quote! {
                     fn #current_sub_tree_fn() -> crate::repl::CompletionTree {
-                        use crate::repl::CompletionTree;
-                        CompletionTree {
-                            branches: #field_type::lazy_completion_tree()()
-                                .branches
-                                .into_iter()
-                                .map(|(s, _)| (s, Box::new(#next_sub_tree_fn) as _))
-                                .collect(),
-                        }
+                        #sub_tree
                     }
                 });
             }
@@ -195,8 +211,8 @@ fn repl_completion_enum(data: DataEnum) -> TokenStream {
             continue;
         }
 
-        // if discriminant, then no field
-        if let Some(_) = variant.discriminant {
+        // if discriminant or unit, then no field
+        if variant.discriminant.is_some() || matches!(variant.fields, Fields::Unit) {
             lines.push(quote! {
                 (stringify!(#variant_name).to_string(), crate::repl::CompletionTree::lazy_empty()),
             });
@@ -210,7 +226,7 @@ fn repl_completion_enum(data: DataEnum) -> TokenStream {
             _ => unimplemented!(),
         }
         .unnamed;
-        let field = match fields.into_iter().nth(0) {
+        let field = match fields.into_iter().next() {
             Some(f) => f,
             None => unimplemented!(),
         };
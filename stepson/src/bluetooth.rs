@@ -66,10 +66,7 @@ impl ReplCompletionStateful for Manager {
     fn lazy_completion_tree(&self) -> Box<dyn FnOnce() -> CompletionTree> {
         let conn = self.conn.clone();
         Box::new(|| {
-            let devices = match Self::get_devices_priv(conn) {
-                Ok(d) => d,
-                Err(_) => vec![],
-            };
+            let devices = Self::get_devices_priv(conn).unwrap_or_default();
             CompletionTree::new(
                 devices
                     .into_iter()
@@ -62,7 +62,7 @@ pub fn socket<T: Into<BtProtocol>>(
 
 pub fn connect(fd: RawFd, addr: &BtAddr) -> Result<()> {
     //TODO understand why we are reversing the MAC address
-    let mut rev = addr.0.rc_bdaddr.clone();
+    let mut rev = addr.0.rc_bdaddr;
     rev.reverse();
     let addr = BtAddr(sockaddr_rc {
         sa_family: addr.0.sa_family,
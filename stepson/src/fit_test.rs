@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::message::data_mdr::fit::{
+    EarpieceFit, FitGetParam, FitInquiredType, FitNtfyParam, FitNtfyStatus, FitRetParam,
+    FitSetStatus, FittingDetectionControl, FittingDetectionStatus,
+};
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message};
+use crate::message_queue::{Canceller, MessageQueue};
+
+/// How long the headset may take to measure, which includes playing the test tone
+const FIT_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum FitTestError {
+    #[error("the fit test stopped before finishing")]
+    Stopped,
+    #[error("the headset did not finish the fit test in time")]
+    Timeout,
+    #[error("the connection closed before the fit test finished")]
+    Disconnected,
+    #[error(transparent)]
+    Send(#[from] anyhow::Error),
+}
+
+/// A running earpiece fit test. Anything received meanwhile that isn't about the test is set aside
+/// as described for `MessageQueue::recv_matching`.
+pub struct FitTest<'a> {
+    message_queue: &'a mut MessageQueue,
+    status: FittingDetectionStatus,
+    fit: Option<EarpieceFit>,
+    done: bool,
+}
+
+/// A notification or answer about the test
+enum Update {
+    Status(FittingDetectionStatus),
+    Fit(EarpieceFit),
+}
+
+impl<'a> FitTest<'a> {
+    pub async fn start(message_queue: &'a mut MessageQueue) -> Result<FitTest<'a>> {
+        message_queue
+            .send(set_status_message(FittingDetectionControl::Start))
+            .await?;
+
+        Ok(Self {
+            message_queue,
+            status: FittingDetectionStatus::NotRunning,
+            fit: None,
+            done: false,
+        })
+    }
+
+    /// Asks the headset to stop the test. `wait` then returns `FitTestError::Stopped`.
+    pub async fn cancel(&mut self) -> Result<()> {
+        self.canceller().cancel().await
+    }
+
+    /// For cancelling while `wait` is being awaited
+    pub fn canceller(&self) -> Canceller {
+        Canceller::new(self.message_queue.sender(), || {
+            set_status_message(FittingDetectionControl::Cancel)
+        })
+    }
+
+    /// Waits for the test to finish, returning the seal of both earpieces. Once it has returned,
+    /// later calls return `FitTestError::Stopped`.
+    pub async fn wait(&mut self) -> Result<EarpieceFit, FitTestError> {
+        if self.done {
+            return Err(FitTestError::Stopped);
+        }
+        let res = match tokio::time::timeout(FIT_TEST_TIMEOUT, self.wait_inner()).await {
+            Ok(res) => res,
+            Err(_) => Err(FitTestError::Timeout),
+        };
+        self.done = true;
+        res
+    }
+
+    async fn wait_inner(&mut self) -> Result<EarpieceFit, FitTestError> {
+        loop {
+            let update = self
+                .message_queue
+                .recv_matching(|message| match &message.data {
+                    Data::DataMdr(DataMdr {
+                        command:
+                            Command::FitNtfyStatus(FitNtfyStatus(
+                                FitInquiredType::EarpieceFittingDetection,
+                                status,
+                            )),
+                    }) => Some(Update::Status(*status)),
+                    Data::DataMdr(DataMdr {
+                        command:
+                            Command::FitNtfyParam(FitNtfyParam(
+                                FitInquiredType::EarpieceFittingDetection,
+                                fit,
+                            ))
+                            | Command::FitRetParam(FitRetParam(
+                                FitInquiredType::EarpieceFittingDetection,
+                                fit,
+                            )),
+                    }) => Some(Update::Fit(*fit)),
+                    _ => None,
+                })
+                .await;
+
+            match update {
+                Some(Update::Status(status)) => {
+                    let previous = std::mem::replace(&mut self.status, status);
+                    match status {
+                        FittingDetectionStatus::Finished => {
+                            if let Some(fit) = self.fit {
+                                return Ok(fit);
+                            }
+                            // the result notification hasn't arrived yet, so ask for it
+                            self.message_queue
+                                .send(Message::data_mdr(Command::FitGetParam(FitGetParam(
+                                    FitInquiredType::EarpieceFittingDetection,
+                                ))))
+                                .await?;
+                        }
+                        // see `NcOptimizer::next`
+                        FittingDetectionStatus::NotRunning
+                            if previous != FittingDetectionStatus::NotRunning =>
+                        {
+                            return Err(FitTestError::Stopped)
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Update::Fit(fit)) => {
+                    if self.status == FittingDetectionStatus::Finished {
+                        return Ok(fit);
+                    }
+                    self.fit = Some(fit);
+                }
+                None => return Err(FitTestError::Disconnected),
+            }
+        }
+    }
+}
+
+fn set_status_message(control: FittingDetectionControl) -> Message {
+    Message::data_mdr(Command::FitSetStatus(FitSetStatus(
+        FitInquiredType::EarpieceFittingDetection,
+        control,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::fit::EarpieceSeal;
+    use crate::test_device;
+
+    const FIT: EarpieceFit = EarpieceFit(EarpieceSeal::Good, EarpieceSeal::Poor);
+
+    fn status(status: FittingDetectionStatus) -> Message {
+        Message::data_mdr(Command::FitNtfyStatus(FitNtfyStatus(
+            FitInquiredType::EarpieceFittingDetection,
+            status,
+        )))
+    }
+
+    /// A headset that measures when started, only answers with the fit when asked for it, and
+    /// stops when told to
+    fn connect(finish: bool) -> (MessageQueue, test_device::TestDevice) {
+        test_device::connect(move |command| match command {
+            Command::FitSetStatus(FitSetStatus(_, FittingDetectionControl::Start)) => {
+                let mut replies = vec![status(FittingDetectionStatus::Measuring)];
+                if finish {
+                    replies.push(status(FittingDetectionStatus::Finished));
+                }
+                replies
+            }
+            Command::FitSetStatus(FitSetStatus(_, FittingDetectionControl::Cancel)) => {
+                vec![status(FittingDetectionStatus::NotRunning)]
+            }
+            Command::FitGetParam(_) => vec![Message::data_mdr(Command::FitRetParam(FitRetParam(
+                FitInquiredType::EarpieceFittingDetection,
+                FIT,
+            )))],
+            _ => vec![],
+        })
+    }
+
+    #[tokio::test]
+    async fn asks_for_the_fit_once_finished() {
+        let (mut message_queue, _device) = connect(true);
+        let mut fit_test = FitTest::start(&mut message_queue).await.unwrap();
+        assert_eq!(fit_test.wait().await.unwrap(), FIT);
+        assert!(matches!(fit_test.wait().await, Err(FitTestError::Stopped)));
+    }
+
+    #[tokio::test]
+    async fn cancel_while_waiting() {
+        let (mut message_queue, _device) = connect(false);
+        let mut fit_test = FitTest::start(&mut message_queue).await.unwrap();
+        let canceller = fit_test.canceller();
+        let (res, cancelled) = tokio::join!(fit_test.wait(), canceller.cancel());
+        cancelled.unwrap();
+        assert!(matches!(res, Err(FitTestError::Stopped)));
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let (mut message_queue, _device) = connect(false);
+        let mut fit_test = FitTest::start(&mut message_queue).await.unwrap();
+        assert!(matches!(fit_test.wait().await, Err(FitTestError::Timeout)));
+    }
+}
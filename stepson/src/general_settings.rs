@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::message::data_mdr::general_setting::{
+    GsCapability, GsGetCapability, GsInquiredType, GsRetCapability,
+};
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message};
+use crate::message_queue::MessageQueue;
+
+/// How long to wait for the description of a single setting. Devices don't answer for settings
+/// they don't have.
+const CAPABILITY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Asks the device to describe each of its general settings, returning the descriptions of the
+/// ones it has. Anything else received meanwhile is set aside as described for
+/// `MessageQueue::recv_matching`.
+pub async fn discover(
+    message_queue: &mut MessageQueue,
+) -> Result<Vec<(GsInquiredType, GsCapability)>> {
+    let mut settings = vec![];
+
+    for &inquired_type in GsInquiredType::ALL.iter() {
+        message_queue
+            .send(Message::data_mdr(Command::GsGetCapability(
+                GsGetCapability(inquired_type),
+            )))
+            .await?;
+
+        let capability = message_queue.recv_matching(|message| match &message.data {
+            Data::DataMdr(DataMdr {
+                command: Command::GsRetCapability(GsRetCapability(t, capability)),
+            }) if *t == inquired_type => Some(capability.clone()),
+            _ => None,
+        });
+        match tokio::time::timeout(CAPABILITY_TIMEOUT, capability).await {
+            Ok(Some(capability)) => settings.push((inquired_type, capability)),
+            Ok(None) => return Err(anyhow!("the connection closed during discovery")),
+            Err(_) => {}
+        }
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::general_setting::{GsString, GsStringFormat, GsValues};
+    use crate::message::{MESSAGE_END, MESSAGE_START};
+    use crate::test_device;
+
+    fn capability() -> GsCapability {
+        GsCapability(
+            GsString(GsStringFormat::Raw, "Touch sensor".to_string()),
+            GsString(GsStringFormat::Raw, "Use the touch sensor".to_string()),
+            GsValues::Boolean,
+        )
+    }
+
+    #[tokio::test]
+    async fn skips_unrelated_errors() {
+        // only the second setting exists
+        let (mut message_queue, device) = test_device::connect(|command| match command {
+            Command::GsGetCapability(GsGetCapability(GsInquiredType::GeneralSetting2)) => {
+                vec![Message::data_mdr(Command::GsRetCapability(
+                    GsRetCapability(GsInquiredType::GeneralSetting2, capability()),
+                ))]
+            }
+            _ => vec![],
+        });
+        device.send_raw(vec![MESSAGE_START, MESSAGE_END]);
+
+        let settings = discover(&mut message_queue).await.unwrap();
+        assert_eq!(
+            settings,
+            vec![(GsInquiredType::GeneralSetting2, capability())]
+        );
+        assert!(matches!(message_queue.recv().await, Some(Err(_))));
+    }
+}
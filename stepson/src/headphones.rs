@@ -0,0 +1,436 @@
+mod state;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::message::data_mdr::alert::AlertNtfyParam;
+use crate::message::data_mdr::common::{
+    AudioCodec, AudioCodecInquiredType, BatteryInquiredType, BatteryStatus, CommonGetAudioCodec,
+    CommonGetBatteryLevel, CommonRetAudioCodec, CommonRetBatteryLevel,
+};
+use crate::message::data_mdr::connect::{ConnectGetProtocolInfo, ConnectInquiredType};
+use crate::message::data_mdr::eq_ebb::{
+    EqCurve, EqEbbGetParam, EqEbbInquiredType, EqEbbRetParam, EqEbbSetParam, EqPresetId, EqSetting,
+};
+use crate::message::data_mdr::nc_asm::{NcAsmGetParam, NcAsmRetParam, NcAsmSetParam, NcAsmSetting};
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message};
+use crate::message_queue::{AlertResponder, MessageQueue, RecvHalf, SendHalf};
+use crate::serializable::ProtocolVersion;
+
+pub use state::DeviceState;
+use state::DeviceStatePublisher;
+
+/// How long to wait for the device to answer a request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many notifications are kept until they are read, later ones are dropped
+const NOTIFICATION_BUFFER: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum HeadphonesError {
+    #[error("the device did not answer in time")]
+    Timeout,
+    #[error("the connection closed before the device answered")]
+    Disconnected,
+    #[error(transparent)]
+    Send(#[from] anyhow::Error),
+}
+
+/// Takes the answer to a request out of a received command, returning whether it did
+type Waiter = Box<dyn FnMut(&Command) -> bool + Send>;
+
+type Waiters = Arc<Mutex<Vec<(u64, Waiter)>>>;
+
+/// A connected device, with a typed method for each request. Received commands are handed to the
+/// request waiting for them, everything else, which is mostly notifications, can be read with
+/// `notification` along with any errors receiving. What the device reports about itself is also kept in a `DeviceState`.
+pub struct Headphones {
+    send_half: SendHalf,
+    state: DeviceState,
+    waiters: Waiters,
+    next_waiter_id: Mutex<u64>,
+    notification_receiver: mpsc::Receiver<Result<Command>>,
+    stop_sender: oneshot::Sender<()>,
+    dispatcher: JoinHandle<RecvHalf>,
+}
+
+impl Headphones {
+    pub fn new(message_queue: MessageQueue) -> Self {
+        let (recv_half, send_half) = message_queue.split();
+        let waiters: Waiters = Arc::new(Mutex::new(vec![]));
+        let (notification_sender, notification_receiver) = mpsc::channel(NOTIFICATION_BUFFER);
+        let (stop_sender, stop_receiver) = oneshot::channel();
+        let (publisher, state) = DeviceStatePublisher::new();
+
+        let dispatcher = tokio::spawn(dispatch(
+            recv_half,
+            publisher,
+            waiters.clone(),
+            notification_sender,
+            stop_receiver,
+        ));
+
+        Self {
+            send_half,
+            state,
+            waiters,
+            next_waiter_id: Mutex::new(0),
+            notification_receiver,
+            stop_sender,
+            dispatcher,
+        }
+    }
+
+    /// The last known state of the device, which is kept up to date for as long as the connection
+    /// is open. Reading it doesn't ask the device for anything.
+    pub fn state(&self) -> DeviceState {
+        self.state.clone()
+    }
+
+    /// Stops handling received messages and hands back the queue, e.g. to run an `NcOptimizer`.
+    /// Notifications that haven't been read yet are lost, and the `DeviceState` is no longer
+    /// updated.
+    pub async fn into_message_queue(self) -> Result<MessageQueue> {
+        let _ = self.stop_sender.send(());
+        let recv_half = self.dispatcher.await?;
+        Ok(recv_half.unsplit(self.send_half))
+    }
+
+    /// Waits for the next received command that didn't answer a request, or the next error
+    /// receiving, e.g. a message that couldn't be deserialized. Returns `None` once the connection
+    /// has closed.
+    pub async fn notification(&mut self) -> Option<Result<Command>> {
+        self.notification_receiver.recv().await
+    }
+
+    /// See `MessageQueue::set_alert_callback`
+    pub fn set_alert_callback<F>(&self, callback: F)
+    where
+        F: Fn(AlertNtfyParam, AlertResponder) + Send + Sync + 'static,
+    {
+        self.send_half.set_alert_callback(callback);
+    }
+
+    /// Asks the device for its protocol version, which messages are (de)serialized for from then
+    /// on
+    pub async fn negotiate_protocol_version(&self) -> Result<ProtocolVersion, HeadphonesError> {
+        self.request(
+            Command::ConnectGetProtocolInfo(ConnectGetProtocolInfo(ConnectInquiredType::Fixed)),
+            |command| match command {
+                Command::ConnectRetProtocolInfo(info) => Some(info.protocol_version()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn noise_control(&self) -> Result<NcAsmSetting, HeadphonesError> {
+        self.request(
+            Command::NcAsmGetParam(NcAsmGetParam {}),
+            |command| match command {
+                Command::NcAsmRetParam(NcAsmRetParam(setting)) => Some(setting.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn set_noise_control(&self, setting: NcAsmSetting) -> Result<(), HeadphonesError> {
+        self.send(Command::NcAsmSetParam(NcAsmSetParam(setting)))
+            .await
+    }
+
+    /// Which batteries there are depends on the model, a device doesn't answer for the others
+    pub async fn battery(
+        &self,
+        inquired_type: BatteryInquiredType,
+    ) -> Result<BatteryStatus, HeadphonesError> {
+        self.request(
+            Command::CommonGetBatteryLevel(CommonGetBatteryLevel(inquired_type)),
+            move |command| match command {
+                Command::CommonRetBatteryLevel(CommonRetBatteryLevel(status))
+                    if status.inquired_type() == inquired_type =>
+                {
+                    Some(*status)
+                }
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn audio_codec(&self) -> Result<AudioCodec, HeadphonesError> {
+        self.request(
+            Command::CommonGetAudioCodec(CommonGetAudioCodec(AudioCodecInquiredType::Fixed)),
+            |command| match command {
+                Command::CommonRetAudioCodec(CommonRetAudioCodec(_, codec)) => Some(*codec),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// The active preset along with its curve
+    pub async fn equalizer(&self) -> Result<(EqPresetId, EqCurve), HeadphonesError> {
+        self.request(
+            Command::EqEbbGetParam(EqEbbGetParam(EqEbbInquiredType::PresetEq)),
+            |command| match command {
+                Command::EqEbbRetParam(EqEbbRetParam(
+                    EqEbbInquiredType::PresetEq,
+                    preset,
+                    curve,
+                )) => Some((*preset, curve.clone())),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn set_equalizer(&self, setting: EqSetting) -> Result<(), HeadphonesError> {
+        self.send(Command::EqEbbSetParam(EqEbbSetParam(
+            EqEbbInquiredType::PresetEq,
+            setting,
+        )))
+        .await
+    }
+
+    /// See `MessageQueue::power_off`
+    pub async fn power_off(&self) -> Result<(), HeadphonesError> {
+        Ok(self.send_half.power_off().await?)
+    }
+
+    async fn send(&self, command: Command) -> Result<(), HeadphonesError> {
+        Ok(self.send_half.send(Message::data_mdr(command)).await?)
+    }
+
+    /// Sends `command` and waits for the first received command `extract` returns something for
+    async fn request<T, F>(&self, command: Command, extract: F) -> Result<T, HeadphonesError>
+    where
+        T: Send + 'static,
+        F: Fn(&Command) -> Option<T> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let mut tx = Some(tx);
+        let waiter: Waiter = Box::new(move |command| match extract(command) {
+            Some(x) => {
+                if let Some(tx) = tx.take() {
+                    let _ = tx.send(x);
+                }
+                true
+            }
+            None => false,
+        });
+
+        let id = {
+            let mut next_waiter_id = self.next_waiter_id.lock().unwrap();
+            *next_waiter_id += 1;
+            *next_waiter_id
+        };
+        // registered before sending, so the answer can't arrive before anyone waits for it
+        self.waiters.lock().unwrap().push((id, waiter));
+
+        let res = match self.send(command).await {
+            Ok(()) => match tokio::time::timeout(RESPONSE_TIMEOUT, rx).await {
+                Ok(Ok(x)) => return Ok(x),
+                Ok(Err(_)) => Err(HeadphonesError::Disconnected),
+                Err(_) => Err(HeadphonesError::Timeout),
+            },
+            Err(e) => Err(e),
+        };
+        self.waiters.lock().unwrap().retain(|(i, _)| *i != id);
+        res
+    }
+}
+
+/// Publishes every received command to the device state, then hands it to the first waiter that
+/// takes it, or passes it on as a notification along with errors, until told to stop or the
+/// connection closes
+async fn dispatch(
+    mut recv_half: RecvHalf,
+    publisher: DeviceStatePublisher,
+    waiters: Waiters,
+    notification_sender: mpsc::Sender<Result<Command>>,
+    mut stop_receiver: oneshot::Receiver<()>,
+) -> RecvHalf {
+    loop {
+        let message = tokio::select! {
+            _ = &mut stop_receiver => break,
+            message = recv_half.recv() => match message {
+                Some(Ok(m)) => m,
+                Some(Err(e)) => {
+                    let _ = notification_sender.try_send(Err(e));
+                    continue;
+                }
+                None => break,
+            },
+        };
+
+        let command = match message.data {
+            Data::DataMdr(DataMdr { command }) | Data::ShotMdr(DataMdr { command }) => command,
+            _ => continue,
+        };
+
+        publisher.update(&command);
+
+        {
+            let mut waiters = waiters.lock().unwrap();
+            if let Some(i) = waiters.iter_mut().position(|(_, w)| w(&command)) {
+                let _ = waiters.remove(i);
+                continue;
+            }
+        }
+
+        // nobody reading notifications mustn't hold up requests
+        let _ = notification_sender.try_send(Ok(command));
+    }
+
+    // requests still waiting fail with `HeadphonesError::Disconnected`
+    waiters.lock().unwrap().clear();
+    recv_half
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::common::{
+        BatteryChargingStatus, BatteryLevel, CommonNtfyBatteryLevel, LeftRightBatteryLevel,
+    };
+    use crate::message::{MESSAGE_END, MESSAGE_START};
+    use crate::test_device;
+
+    fn battery(level: u8) -> BatteryStatus {
+        BatteryStatus::Battery(BatteryLevel(level, BatteryChargingStatus::NotCharging))
+    }
+
+    #[tokio::test]
+    async fn answers_requests() {
+        let (message_queue, _device) = test_device::connect(|command| match command {
+            Command::CommonGetAudioCodec(CommonGetAudioCodec(t)) => vec![Message::data_mdr(
+                Command::CommonRetAudioCodec(CommonRetAudioCodec(*t, AudioCodec::Ldac)),
+            )],
+            _ => vec![],
+        });
+        let headphones = Headphones::new(message_queue);
+        assert_eq!(headphones.audio_codec().await.unwrap(), AudioCodec::Ldac);
+        assert_eq!(
+            *headphones.state().audio_codec.borrow(),
+            Some(AudioCodec::Ldac)
+        );
+    }
+
+    #[tokio::test]
+    async fn state_stops_changing_with_the_dispatcher() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let headphones = Headphones::new(message_queue);
+        let mut state = headphones.state();
+        let message_queue = headphones.into_message_queue().await.unwrap();
+        assert!(state.battery.changed().await.is_err());
+
+        // a disconnect stops it as well
+        let mut headphones = Headphones::new(message_queue);
+        let mut state = headphones.state();
+        device.disconnect();
+        while headphones.notification().await.is_some() {}
+        assert!(state.battery.changed().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let (message_queue, _device) = test_device::connect(|_| vec![]);
+        let headphones = Headphones::new(message_queue);
+        assert!(matches!(
+            headphones.audio_codec().await,
+            Err(HeadphonesError::Timeout)
+        ));
+        assert!(headphones.waiters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn disconnected_while_waiting() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let headphones = Headphones::new(message_queue);
+        let disconnect = async {
+            while device.received().is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            device.disconnect();
+        };
+        let (res, ()) = tokio::join!(headphones.audio_codec(), disconnect);
+        assert!(matches!(res, Err(HeadphonesError::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn answer_for_another_inquired_type_is_a_notification() {
+        let left_right = BatteryStatus::LeftRightBattery(LeftRightBatteryLevel(
+            BatteryLevel(50, BatteryChargingStatus::NotCharging),
+            BatteryLevel(60, BatteryChargingStatus::Charging),
+        ));
+        let (message_queue, _device) = test_device::connect(move |command| match command {
+            Command::CommonGetBatteryLevel(_) => vec![Message::data_mdr(
+                Command::CommonRetBatteryLevel(CommonRetBatteryLevel(left_right)),
+            )],
+            _ => vec![],
+        });
+        let mut headphones = Headphones::new(message_queue);
+        assert!(matches!(
+            headphones.battery(BatteryInquiredType::Battery).await,
+            Err(HeadphonesError::Timeout)
+        ));
+        assert!(matches!(
+            headphones.notification().await,
+            Some(Ok(Command::CommonRetBatteryLevel(CommonRetBatteryLevel(status))))
+                if status == left_right
+        ));
+    }
+
+    #[tokio::test]
+    async fn notifications_overflow_without_holding_up_requests() {
+        let count = NOTIFICATION_BUFFER as u8 + 10;
+        let (message_queue, device) = test_device::connect(move |command| match command {
+            Command::CommonGetAudioCodec(CommonGetAudioCodec(t)) => (0..count)
+                .map(|i| {
+                    Message::data_mdr(Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(
+                        battery(i),
+                    )))
+                })
+                .chain(std::iter::once(Message::data_mdr(
+                    Command::CommonRetAudioCodec(CommonRetAudioCodec(*t, AudioCodec::Sbc)),
+                )))
+                .collect(),
+            _ => vec![],
+        });
+        let mut headphones = Headphones::new(message_queue);
+        assert_eq!(headphones.audio_codec().await.unwrap(), AudioCodec::Sbc);
+
+        // the oldest ones are kept
+        for i in 0..NOTIFICATION_BUFFER as u8 {
+            assert!(matches!(
+                headphones.notification().await,
+                Some(Ok(Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(status))))
+                    if status == battery(i)
+            ));
+        }
+        // while the latest state is still published
+        assert_eq!(
+            *headphones.state().battery.borrow(),
+            Some(BatteryLevel(count - 1, BatteryChargingStatus::NotCharging))
+        );
+
+        device.disconnect();
+        assert!(matches!(headphones.notification().await, Some(Err(_))));
+        assert!(headphones.notification().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn passes_on_errors() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let mut headphones = Headphones::new(message_queue);
+        device.send_raw(vec![MESSAGE_START, MESSAGE_END]);
+        assert!(matches!(headphones.notification().await, Some(Err(_))));
+    }
+}
@@ -0,0 +1,328 @@
+use tokio::sync::watch;
+
+use crate::message::data_mdr::common::{
+    AudioCodec, BatteryLevel, BatteryStatus, CommonNtfyAudioCodec, CommonNtfyBatteryLevel,
+    CommonNtfyUpscalingEffect, CommonRetAudioCodec, CommonRetBatteryLevel,
+    CommonRetUpscalingEffect, LeftRightBatteryLevel, UpscalingEffect, UpscalingType,
+};
+use crate::message::data_mdr::eq_ebb::{
+    EqCurve, EqEbbInquiredType, EqEbbNtfyParam, EqEbbRetParam, EqPresetId,
+};
+use crate::message::data_mdr::nc_asm::{NcAsmNtfyParam, NcAsmRetParam, NcAsmSetting};
+use crate::message::data_mdr::Command;
+use crate::serializable::ProtocolVersion;
+
+/// The last known state of a connected device. Each value is `None` until the device has reported
+/// it, either answering a request or on its own when it changes. Values are only published when
+/// they change, so `changed` on a receiver means something is actually different. Once the
+/// connection closes, `changed` returns an error and the values stay as they were.
+#[derive(Clone)]
+pub struct DeviceState {
+    pub protocol_version: watch::Receiver<Option<ProtocolVersion>>,
+    pub noise_control: watch::Receiver<Option<NcAsmSetting>>,
+    pub battery: watch::Receiver<Option<BatteryLevel>>,
+    pub left_right_battery: watch::Receiver<Option<LeftRightBatteryLevel>>,
+    pub cradle_battery: watch::Receiver<Option<BatteryLevel>>,
+    /// The active preset along with its curve
+    pub equalizer: watch::Receiver<Option<(EqPresetId, EqCurve)>>,
+    pub audio_codec: watch::Receiver<Option<AudioCodec>>,
+    pub upscaling: watch::Receiver<Option<(UpscalingType, UpscalingEffect)>>,
+}
+
+/// Updates a `DeviceState` from the commands the device sends
+pub(super) struct DeviceStatePublisher {
+    protocol_version: Slot<ProtocolVersion>,
+    noise_control: Slot<NcAsmSetting>,
+    battery: Slot<BatteryLevel>,
+    left_right_battery: Slot<LeftRightBatteryLevel>,
+    cradle_battery: Slot<BatteryLevel>,
+    equalizer: Slot<(EqPresetId, EqCurve)>,
+    audio_codec: Slot<AudioCodec>,
+    upscaling: Slot<(UpscalingType, UpscalingEffect)>,
+}
+
+impl DeviceStatePublisher {
+    pub fn new() -> (Self, DeviceState) {
+        let (protocol_version, protocol_version_receiver) = Slot::new();
+        let (noise_control, noise_control_receiver) = Slot::new();
+        let (battery, battery_receiver) = Slot::new();
+        let (left_right_battery, left_right_battery_receiver) = Slot::new();
+        let (cradle_battery, cradle_battery_receiver) = Slot::new();
+        let (equalizer, equalizer_receiver) = Slot::new();
+        let (audio_codec, audio_codec_receiver) = Slot::new();
+        let (upscaling, upscaling_receiver) = Slot::new();
+
+        (
+            Self {
+                protocol_version,
+                noise_control,
+                battery,
+                left_right_battery,
+                cradle_battery,
+                equalizer,
+                audio_codec,
+                upscaling,
+            },
+            DeviceState {
+                protocol_version: protocol_version_receiver,
+                noise_control: noise_control_receiver,
+                battery: battery_receiver,
+                left_right_battery: left_right_battery_receiver,
+                cradle_battery: cradle_battery_receiver,
+                equalizer: equalizer_receiver,
+                audio_codec: audio_codec_receiver,
+                upscaling: upscaling_receiver,
+            },
+        )
+    }
+
+    /// Publishes whatever `command` reports about the device. Commands that don't describe the
+    /// device's state are ignored.
+    pub fn update(&self, command: &Command) {
+        match command {
+            Command::ConnectRetProtocolInfo(info) => {
+                self.protocol_version.set(info.protocol_version())
+            }
+            Command::NcAsmRetParam(NcAsmRetParam(setting))
+            | Command::NcAsmNtfyParam(NcAsmNtfyParam(setting)) => {
+                self.noise_control.set(setting.clone())
+            }
+            Command::CommonRetBatteryLevel(CommonRetBatteryLevel(status))
+            | Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(status)) => match *status {
+                BatteryStatus::Battery(level) => self.battery.set(level),
+                BatteryStatus::LeftRightBattery(levels) => self.left_right_battery.set(levels),
+                BatteryStatus::CradleBattery(level) => self.cradle_battery.set(level),
+            },
+            Command::EqEbbRetParam(EqEbbRetParam(EqEbbInquiredType::PresetEq, preset, curve))
+            | Command::EqEbbNtfyParam(EqEbbNtfyParam(EqEbbInquiredType::PresetEq, preset, curve)) => {
+                self.equalizer.set((*preset, curve.clone()))
+            }
+            Command::CommonRetAudioCodec(CommonRetAudioCodec(_, codec))
+            | Command::CommonNtfyAudioCodec(CommonNtfyAudioCodec(_, codec)) => {
+                self.audio_codec.set(*codec)
+            }
+            Command::CommonRetUpscalingEffect(CommonRetUpscalingEffect(_, upscaling, effect))
+            | Command::CommonNtfyUpscalingEffect(CommonNtfyUpscalingEffect(_, upscaling, effect)) => {
+                self.upscaling.set((*upscaling, *effect))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A single published value
+struct Slot<T>(watch::Sender<Option<T>>);
+
+impl<T: PartialEq> Slot<T> {
+    fn new() -> (Self, watch::Receiver<Option<T>>) {
+        let (sender, receiver) = watch::channel(None);
+        (Self(sender), receiver)
+    }
+
+    fn set(&self, value: T) {
+        self.0.send_if_modified(|current| {
+            if current.as_ref() == Some(&value) {
+                return false;
+            }
+            *current = Some(value);
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::message::data_mdr::common::{
+        AudioCodecInquiredType, BatteryChargingStatus, CommonGetAudioCodec, UpscalingInquiredType,
+    };
+    use crate::message::data_mdr::connect::{ConnectInquiredType, ConnectRetProtocolInfo};
+    use crate::message::data_mdr::eq_ebb::{EqBands, EQ_BAND_COUNT};
+    use crate::message::data_mdr::nc_asm::{AsmId, NcAsm, NcAsmEffect, NcDualSingleValue};
+
+    /// The values of `state` that have been published
+    fn published(state: &DeviceState) -> Vec<&'static str> {
+        let mut published = vec![];
+        if state.protocol_version.borrow().is_some() {
+            published.push("protocol_version");
+        }
+        if state.noise_control.borrow().is_some() {
+            published.push("noise_control");
+        }
+        if state.battery.borrow().is_some() {
+            published.push("battery");
+        }
+        if state.left_right_battery.borrow().is_some() {
+            published.push("left_right_battery");
+        }
+        if state.cradle_battery.borrow().is_some() {
+            published.push("cradle_battery");
+        }
+        if state.equalizer.borrow().is_some() {
+            published.push("equalizer");
+        }
+        if state.audio_codec.borrow().is_some() {
+            published.push("audio_codec");
+        }
+        if state.upscaling.borrow().is_some() {
+            published.push("upscaling");
+        }
+        published
+    }
+
+    fn audio_codec(codec: AudioCodec) -> Command {
+        Command::CommonNtfyAudioCodec(CommonNtfyAudioCodec(AudioCodecInquiredType::Fixed, codec))
+    }
+
+    #[test]
+    fn updates_the_matching_value() {
+        let level = BatteryLevel(40, BatteryChargingStatus::Charging);
+        let nc_asm = NcAsmSetting::NcAsm(NcAsm(
+            NcAsmEffect::On,
+            NcDualSingleValue::Dual,
+            AsmId::Normal,
+            0,
+        ));
+        let curve = EqCurve::Bands(EqBands::new([0; EQ_BAND_COUNT], 0).unwrap());
+        let upscaling = (
+            UpscalingInquiredType::Fixed,
+            UpscalingType::DseeHx,
+            UpscalingEffect::On,
+        );
+        let cases = vec![
+            (
+                Command::ConnectRetProtocolInfo(ConnectRetProtocolInfo(
+                    ConnectInquiredType::Fixed,
+                    0x0100,
+                )),
+                vec!["protocol_version"],
+            ),
+            (
+                Command::NcAsmRetParam(NcAsmRetParam(nc_asm.clone())),
+                vec!["noise_control"],
+            ),
+            (
+                Command::NcAsmNtfyParam(NcAsmNtfyParam(nc_asm)),
+                vec!["noise_control"],
+            ),
+            (
+                Command::CommonRetBatteryLevel(CommonRetBatteryLevel(BatteryStatus::Battery(
+                    level,
+                ))),
+                vec!["battery"],
+            ),
+            (
+                Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(
+                    BatteryStatus::LeftRightBattery(LeftRightBatteryLevel(level, level)),
+                )),
+                vec!["left_right_battery"],
+            ),
+            (
+                Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(
+                    BatteryStatus::CradleBattery(level),
+                )),
+                vec!["cradle_battery"],
+            ),
+            (
+                Command::EqEbbRetParam(EqEbbRetParam(
+                    EqEbbInquiredType::PresetEq,
+                    EqPresetId::Rock,
+                    curve.clone(),
+                )),
+                vec!["equalizer"],
+            ),
+            (
+                Command::EqEbbNtfyParam(EqEbbNtfyParam(
+                    EqEbbInquiredType::PresetEq,
+                    EqPresetId::Rock,
+                    curve.clone(),
+                )),
+                vec!["equalizer"],
+            ),
+            // only the preset equalizer is kept
+            (
+                Command::EqEbbNtfyParam(EqEbbNtfyParam(
+                    EqEbbInquiredType::Ebb,
+                    EqPresetId::Rock,
+                    EqCurve::Empty,
+                )),
+                vec![],
+            ),
+            (
+                Command::CommonRetAudioCodec(CommonRetAudioCodec(
+                    AudioCodecInquiredType::Fixed,
+                    AudioCodec::Aac,
+                )),
+                vec!["audio_codec"],
+            ),
+            (audio_codec(AudioCodec::Aac), vec!["audio_codec"]),
+            (
+                Command::CommonRetUpscalingEffect(CommonRetUpscalingEffect(
+                    upscaling.0,
+                    upscaling.1,
+                    upscaling.2,
+                )),
+                vec!["upscaling"],
+            ),
+            (
+                Command::CommonNtfyUpscalingEffect(CommonNtfyUpscalingEffect(
+                    upscaling.0,
+                    upscaling.1,
+                    upscaling.2,
+                )),
+                vec!["upscaling"],
+            ),
+            (
+                Command::CommonGetAudioCodec(CommonGetAudioCodec(AudioCodecInquiredType::Fixed)),
+                vec![],
+            ),
+        ];
+
+        for (command, expected) in cases {
+            let (publisher, state) = DeviceStatePublisher::new();
+            publisher.update(&command);
+            assert_eq!(published(&state), expected, "{:?}", command);
+        }
+    }
+
+    #[test]
+    fn only_changes_wake_receivers() {
+        let (publisher, mut state) = DeviceStatePublisher::new();
+        publisher.update(&audio_codec(AudioCodec::Sbc));
+        assert!(matches!(
+            state.audio_codec.changed().now_or_never(),
+            Some(Ok(()))
+        ));
+
+        publisher.update(&audio_codec(AudioCodec::Sbc));
+        assert!(state.audio_codec.changed().now_or_never().is_none());
+
+        publisher.update(&audio_codec(AudioCodec::Ldac));
+        assert!(matches!(
+            state.audio_codec.changed().now_or_never(),
+            Some(Ok(()))
+        ));
+        assert_eq!(*state.audio_codec.borrow(), Some(AudioCodec::Ldac));
+        // nothing else was touched
+        assert!(state.battery.changed().now_or_never().is_none());
+    }
+
+    #[test]
+    fn values_stay_once_the_publisher_is_gone() {
+        let (publisher, mut state) = DeviceStatePublisher::new();
+        publisher.update(&audio_codec(AudioCodec::Sbc));
+        assert!(matches!(
+            state.audio_codec.changed().now_or_never(),
+            Some(Ok(()))
+        ));
+        drop(publisher);
+
+        assert!(matches!(
+            state.audio_codec.changed().now_or_never(),
+            Some(Err(_))
+        ));
+        assert_eq!(*state.audio_codec.borrow(), Some(AudioCodec::Sbc));
+    }
+}
@@ -1,8 +1,14 @@
 pub mod bluetooth;
+pub mod fit_test;
+pub mod general_settings;
+pub mod headphones;
 pub mod message;
 pub mod message_queue;
+pub mod nc_optimizer;
 pub mod repl;
 pub mod serializable;
+#[cfg(test)]
+mod test_device;
 
 use repl::Repl;
 
@@ -1,12 +1,13 @@
 pub mod ack;
 pub mod data_mdr;
+pub mod data_mdr_no2;
 
 use std::convert::TryInto;
 
 use num_enum::{FromPrimitive, IntoPrimitive};
 
 use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
-use crate::serializable::{DeserializeError, Serializable};
+use crate::serializable::{DeserializeError, ProtocolVersion, Serializable};
 
 /// com.sony.songpal.tandemfamily.DataType
 #[derive(Clone, Copy, Debug, IntoPrimitive, FromPrimitive, PartialEq, Eq)]
@@ -14,6 +15,10 @@ use crate::serializable::{DeserializeError, Serializable};
 pub enum DataType {
     Ack = 1,
     DataMdr = 12,
+    DataMdrNo2 = 14,
+    Shot = 16,
+    ShotMdr = 28,
+    ShotMdrNo2 = 30,
     #[num_enum(default)]
     Unknown,
 }
@@ -22,6 +27,14 @@ pub enum DataType {
 pub enum Data {
     Ack(ack::Ack),
     DataMdr(data_mdr::DataMdr),
+    DataMdrNo2(data_mdr_no2::DataMdrNo2),
+    /// Shots are never acknowledged, which suits frequent notifications where a lost one doesn't
+    /// matter
+    Shot(Vec<u8>),
+    /// Same commands as `DataMdr`, without an ack
+    ShotMdr(data_mdr::DataMdr),
+    /// Same commands as `DataMdrNo2`, without an ack
+    ShotMdrNo2(data_mdr_no2::DataMdrNo2),
     Unknown(Vec<u8>),
 }
 
@@ -30,6 +43,10 @@ impl Data {
         match self {
             Data::Ack(_) => DataType::Ack,
             Data::DataMdr(_) => DataType::DataMdr,
+            Data::DataMdrNo2(_) => DataType::DataMdrNo2,
+            Data::Shot(_) => DataType::Shot,
+            Data::ShotMdr(_) => DataType::ShotMdr,
+            Data::ShotMdrNo2(_) => DataType::ShotMdrNo2,
             Data::Unknown(_) => DataType::Unknown,
         }
     }
@@ -46,11 +63,16 @@ impl Data {
 // )
 // MESSAGE_END
 //
+// The data length and checksum are of the unescaped bytes. Special bytes are escaped by prefixing
+// them with ESCAPE_CHAR and clearing ESCAPE_MASK, so that MESSAGE_END never appears inside a
+// message.
+//
 // escape / unescape specials is in com.sony.songpal.tandemfamily.message.a.b
 
 pub const MESSAGE_START: u8 = 62;
 pub const MESSAGE_END: u8 = 60;
 pub const ESCAPE_CHAR: u8 = 61;
+const ESCAPE_MASK: u8 = 0b1110_1111;
 
 /// com.sony.songpal.tandemfamily.message.b
 #[derive(Debug)]
@@ -60,12 +82,28 @@ pub struct Message {
 }
 
 impl Message {
-    pub fn requires_ack(&self) -> bool {
-        match self.data.data_type() {
-            DataType::DataMdr => true,
-            _ => false,
+    /// A `DataMdr` message carrying `command`. The queue fills in the sequence number when it is
+    /// sent.
+    pub fn data_mdr(command: data_mdr::Command) -> Self {
+        Self {
+            sequence_number: 0,
+            data: Data::DataMdr(data_mdr::DataMdr { command }),
         }
     }
+
+    pub fn requires_ack(&self) -> bool {
+        matches!(
+            self.data.data_type(),
+            DataType::DataMdr | DataType::DataMdrNo2
+        )
+    }
+
+    pub fn is_shot(&self) -> bool {
+        matches!(
+            self.data.data_type(),
+            DataType::Shot | DataType::ShotMdr | DataType::ShotMdrNo2
+        )
+    }
 }
 
 impl FromRepl for Message {
@@ -88,52 +126,79 @@ impl ReplCompletion for Message {
 
 impl Serializable for Message {
     fn serialize(&self) -> Vec<u8> {
-        let mut data = escape_specials(&match &self.data {
+        self.serialize_for(ProtocolVersion::default())
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_for(bytes, ProtocolVersion::default())
+    }
+
+    fn serialize_for(&self, version: ProtocolVersion) -> Vec<u8> {
+        let mut data = match &self.data {
             Data::Ack(x) => x.serialize(),
-            Data::DataMdr(x) => x.serialize(),
+            Data::DataMdr(x) => x.serialize_for(version),
+            Data::DataMdrNo2(x) => x.serialize(),
+            Data::Shot(x) => x.clone(),
+            Data::ShotMdr(x) => x.serialize_for(version),
+            Data::ShotMdrNo2(x) => x.serialize(),
             Data::Unknown(x) => x.clone(),
-        });
+        };
 
-        // message start, data type, sequence number
-        let mut ret = vec![
-            MESSAGE_START,
-            self.data.data_type().into(),
-            self.sequence_number.into(),
-        ];
+        // data type, sequence number
+        let mut body = vec![self.data.data_type().into(), self.sequence_number];
 
         // data length
-        ret.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
 
         // data
-        ret.append(&mut data);
+        body.append(&mut data);
 
         // checksum
-        ret.push(checksum(&ret[1..]));
+        body.push(checksum(&body));
 
-        // message end
+        let mut ret = vec![MESSAGE_START];
+        ret.append(&mut escape_specials(&body));
         ret.push(MESSAGE_END);
-
         ret
     }
 
-    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+    fn deserialize_for(bytes: &[u8], version: ProtocolVersion) -> Result<Self, DeserializeError> {
         let bytes = unescape_specials(bytes)?;
 
+        if bytes.len() < 9 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+
         if bytes[0] != MESSAGE_START {
             return Err(DeserializeError::InvalidStartOfMessage(bytes[0]));
         }
 
         let data_type = DataType::from(bytes[1]);
         let sequence_number = bytes[2];
-        let data_len = u32::from_be_bytes(bytes[3..7].try_into().unwrap()); //TODO
+        let data_len = u32::from_be_bytes(bytes[3..7].try_into().unwrap());
+        if bytes.len() != data_len as usize + 9 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
         let data = match data_type {
             DataType::Ack => Data::Ack(ack::Ack::deserialize(&bytes[7..(7 + data_len as usize)])?),
-            DataType::DataMdr => Data::DataMdr(data_mdr::DataMdr::deserialize(
+            DataType::DataMdr => Data::DataMdr(data_mdr::DataMdr::deserialize_for(
+                &bytes[7..(7 + data_len as usize)],
+                version,
+            )?),
+            DataType::DataMdrNo2 => Data::DataMdrNo2(data_mdr_no2::DataMdrNo2::deserialize(
+                &bytes[7..(7 + data_len as usize)],
+            )?),
+            DataType::Shot => Data::Shot(bytes[7..(7 + data_len as usize)].to_vec()),
+            DataType::ShotMdr => Data::ShotMdr(data_mdr::DataMdr::deserialize_for(
+                &bytes[7..(7 + data_len as usize)],
+                version,
+            )?),
+            DataType::ShotMdrNo2 => Data::ShotMdrNo2(data_mdr_no2::DataMdrNo2::deserialize(
                 &bytes[7..(7 + data_len as usize)],
             )?),
             DataType::Unknown => Data::Unknown(bytes[7..(7 + data_len as usize)].to_vec()),
         };
-        let chksum = bytes[(7 + data_len as usize)];
+        let chksum = bytes[7 + data_len as usize];
 
         if chksum != checksum(&bytes[1..(7 + data_len as usize)]) {
             return Err(DeserializeError::InvalidChecksum(chksum));
@@ -153,7 +218,7 @@ impl Serializable for Message {
 }
 
 fn checksum(s: &[u8]) -> u8 {
-    s.iter().sum()
+    s.iter().fold(0, |acc, b| acc.wrapping_add(*b))
 }
 
 fn escape_specials(s: &[u8]) -> Vec<u8> {
@@ -162,10 +227,10 @@ fn escape_specials(s: &[u8]) -> Vec<u8> {
         match b {
             &MESSAGE_START | &MESSAGE_END | &ESCAPE_CHAR => {
                 new.push(ESCAPE_CHAR);
+                new.push(b & ESCAPE_MASK);
             }
-            _ => {}
+            _ => new.push(*b),
         }
-        new.push(*b);
     }
     new
 }
@@ -173,11 +238,7 @@ fn escape_specials(s: &[u8]) -> Vec<u8> {
 fn unescape_specials(s: &[u8]) -> Result<Vec<u8>, DeserializeError> {
     let mut new = Vec::new();
     let mut iter = s.iter();
-    loop {
-        let b = match iter.next() {
-            Some(b) => b,
-            None => break,
-        };
+    while let Some(b) = iter.next() {
         match b {
             //&MESSAGE_START | &MESSAGE_END => return Err(DeserializeError::ExpectedEscape(*b)),
             &ESCAPE_CHAR => {
@@ -185,9 +246,10 @@ fn unescape_specials(s: &[u8]) -> Result<Vec<u8>, DeserializeError> {
                     Some(b) => b,
                     None => return Err(DeserializeError::EscapeEof),
                 };
-                match escaped {
-                    &MESSAGE_START | &MESSAGE_END => {
-                        new.push(*escaped);
+                let unescaped = escaped | !ESCAPE_MASK;
+                match unescaped {
+                    MESSAGE_START | MESSAGE_END | ESCAPE_CHAR if unescaped != *escaped => {
+                        new.push(unescaped);
                     }
                     _ => return Err(DeserializeError::InvalidEscape(*escaped)),
                 }
@@ -197,3 +259,63 @@ fn unescape_specials(s: &[u8]) -> Result<Vec<u8>, DeserializeError> {
     }
     Ok(new)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let escaped = escape_specials(&bytes);
+        assert!(!escaped.contains(&MESSAGE_START));
+        assert!(!escaped.contains(&MESSAGE_END));
+        assert_eq!(escaped.len(), bytes.len() + 3);
+        assert_eq!(unescape_specials(&escaped).unwrap(), bytes);
+    }
+
+    #[test]
+    fn rejects_bad_escapes() {
+        assert!(matches!(
+            unescape_specials(&[1, ESCAPE_CHAR]),
+            Err(DeserializeError::EscapeEof)
+        ));
+        // only the three special bytes are escaped
+        assert!(matches!(
+            unescape_specials(&[ESCAPE_CHAR, 0]),
+            Err(DeserializeError::InvalidEscape(0))
+        ));
+    }
+
+    #[test]
+    fn message_round_trip() {
+        // the payload and checksum both need escaping
+        let message = Message {
+            sequence_number: 1,
+            data: Data::Shot(vec![MESSAGE_START, ESCAPE_CHAR, MESSAGE_END, 0, 255]),
+        };
+        let bytes = message.serialize();
+        assert_eq!(bytes.first(), Some(&MESSAGE_START));
+        assert_eq!(bytes.last(), Some(&MESSAGE_END));
+        assert!(!bytes[1..bytes.len() - 1].contains(&MESSAGE_END));
+
+        let deserialized = Message::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized.sequence_number, 1);
+        assert_eq!(deserialized.serialize(), bytes);
+    }
+
+    #[test]
+    fn reports_the_real_length() {
+        let mut bytes = Message {
+            sequence_number: 0,
+            data: Data::Shot(vec![0; 300]),
+        }
+        .serialize();
+        // drop a byte of the payload
+        bytes.remove(10);
+        assert!(matches!(
+            Message::deserialize(&bytes),
+            Err(DeserializeError::InvalidLength(308))
+        ));
+    }
+}
@@ -27,7 +27,7 @@ impl Serializable for Ack {
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
         match bytes.len() {
             0 => Ok(Self {}),
-            _ => Err(DeserializeError::InvalidLength(bytes[0])),
+            n => Err(DeserializeError::InvalidLength(n)),
         }
     }
 }
@@ -1,29 +1,245 @@
+pub mod alert;
+pub mod audio;
+pub mod common;
+pub mod connect;
+pub mod eq_ebb;
+pub mod fit;
+pub mod general_setting;
 pub mod nc_asm;
+pub mod opt;
+pub mod play;
+pub mod system;
+pub mod vpt;
 
 use num_enum::{FromPrimitive, IntoPrimitive};
 
 use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
-use crate::serializable::{DeserializeError, Serializable};
+use crate::serializable::{DeserializeError, ProtocolVersion, Serializable};
 
 /// com.sony.songpal.tandemfamily.message.mdr.v1.table1.Command
 #[derive(Clone, Copy, Debug, IntoPrimitive, FromPrimitive, PartialEq, Eq)]
 #[repr(u8)]
 pub enum CommandType {
-    //NcAsmGetParam = 102, // Noise Cancelling and/or Ambient Sound Mode
+    ConnectGetProtocolInfo = 0,
+    ConnectRetProtocolInfo = 1,
+    CommonGetBatteryLevel = 16,
+    CommonRetBatteryLevel = 17,
+    CommonNtfyBatteryLevel = 19,
+    CommonGetUpscalingEffect = 20,
+    CommonRetUpscalingEffect = 21,
+    CommonSetUpscalingEffect = 22,
+    CommonNtfyUpscalingEffect = 23,
+    CommonGetAudioCodec = 24,
+    CommonRetAudioCodec = 25,
+    CommonNtfyAudioCodec = 27,
+    CommonSetPowerOff = 34,
+    VptGetParam = 70,
+    VptRetParam = 71,
+    VptSetParam = 72,
+    VptNtfyParam = 73,
+    EqEbbGetParam = 86,
+    EqEbbRetParam = 87,
+    EqEbbSetParam = 88,
+    EqEbbNtfyParam = 89,
+    NcAsmGetParam = 102, // Noise Cancelling and/or Ambient Sound Mode
+    NcAsmRetParam = 103,
     NcAsmSetParam = 104,
     NcAsmNtfyParam = 105,
+    OptSetStatus = 132,
+    OptNtfyStatus = 133,
+    OptGetParam = 134,
+    OptRetParam = 135,
+    OptNtfyParam = 137,
+    AlertSetStatus = 148,
+    AlertSetParam = 152,
+    AlertNtfyParam = 153,
+    PlayGetStatus = 162,
+    PlayRetStatus = 163,
+    PlaySetStatus = 164,
+    PlayNtfyStatus = 165,
+    PlayGetParam = 166,
+    PlayRetParam = 167,
+    PlaySetParam = 168,
+    PlayNtfyParam = 169,
+    FitSetStatus = 196,
+    FitNtfyStatus = 197,
+    FitGetParam = 198,
+    FitRetParam = 199,
+    FitNtfyParam = 201,
+    GsGetCapability = 208,
+    GsRetCapability = 209,
+    GsGetParam = 214,
+    GsRetParam = 215,
+    GsSetParam = 216,
+    GsNtfyParam = 217,
+    AudioGetParam = 230,
+    AudioRetParam = 231,
+    AudioSetParam = 232,
+    AudioNtfyParam = 233,
+    SystemGetStatus = 242,
+    SystemRetStatus = 243,
+    SystemNtfyStatus = 245,
+    SystemGetParam = 246,
+    SystemRetParam = 247,
+    SystemSetParam = 248,
+    SystemNtfyParam = 249,
+    SystemGetExtParam = 250,
+    SystemRetExtParam = 251,
+    SystemSetExtParam = 252,
+    SystemNtfyExtParam = 253,
     #[num_enum(default)]
     Unknown,
 }
 
 #[derive(Debug, FromRepl)]
 pub enum Command {
-    //NcAsmGetParam(nc_asm::NcAsmGetParam),
+    ConnectGetProtocolInfo(connect::ConnectGetProtocolInfo),
+    ConnectRetProtocolInfo(connect::ConnectRetProtocolInfo),
+    CommonGetBatteryLevel(common::CommonGetBatteryLevel),
+    CommonRetBatteryLevel(common::CommonRetBatteryLevel),
+    CommonNtfyBatteryLevel(common::CommonNtfyBatteryLevel),
+    CommonGetUpscalingEffect(common::CommonGetUpscalingEffect),
+    CommonRetUpscalingEffect(common::CommonRetUpscalingEffect),
+    CommonSetUpscalingEffect(common::CommonSetUpscalingEffect),
+    CommonNtfyUpscalingEffect(common::CommonNtfyUpscalingEffect),
+    CommonGetAudioCodec(common::CommonGetAudioCodec),
+    CommonRetAudioCodec(common::CommonRetAudioCodec),
+    CommonNtfyAudioCodec(common::CommonNtfyAudioCodec),
+    CommonSetPowerOff(common::CommonSetPowerOff),
+    VptGetParam(vpt::VptGetParam),
+    VptRetParam(vpt::VptRetParam),
+    VptSetParam(vpt::VptSetParam),
+    VptNtfyParam(vpt::VptNtfyParam),
+    EqEbbGetParam(eq_ebb::EqEbbGetParam),
+    EqEbbRetParam(eq_ebb::EqEbbRetParam),
+    EqEbbSetParam(eq_ebb::EqEbbSetParam),
+    EqEbbNtfyParam(eq_ebb::EqEbbNtfyParam),
+    NcAsmGetParam(nc_asm::NcAsmGetParam),
+    NcAsmRetParam(nc_asm::NcAsmRetParam),
     NcAsmSetParam(nc_asm::NcAsmSetParam),
     NcAsmNtfyParam(nc_asm::NcAsmNtfyParam),
+    OptSetStatus(opt::OptSetStatus),
+    OptNtfyStatus(opt::OptNtfyStatus),
+    OptGetParam(opt::OptGetParam),
+    OptRetParam(opt::OptRetParam),
+    OptNtfyParam(opt::OptNtfyParam),
+    AlertSetStatus(alert::AlertSetStatus),
+    AlertSetParam(alert::AlertSetParam),
+    AlertNtfyParam(alert::AlertNtfyParam),
+    PlayGetStatus(play::PlayGetStatus),
+    PlayRetStatus(play::PlayRetStatus),
+    PlaySetStatus(play::PlaySetStatus),
+    PlayNtfyStatus(play::PlayNtfyStatus),
+    PlayGetParam(play::PlayGetParam),
+    PlayRetParam(play::PlayRetParam),
+    PlaySetParam(play::PlaySetParam),
+    PlayNtfyParam(play::PlayNtfyParam),
+    FitSetStatus(fit::FitSetStatus),
+    FitNtfyStatus(fit::FitNtfyStatus),
+    FitGetParam(fit::FitGetParam),
+    FitRetParam(fit::FitRetParam),
+    FitNtfyParam(fit::FitNtfyParam),
+    GsGetCapability(general_setting::GsGetCapability),
+    GsRetCapability(general_setting::GsRetCapability),
+    GsGetParam(general_setting::GsGetParam),
+    GsRetParam(general_setting::GsRetParam),
+    GsSetParam(general_setting::GsSetParam),
+    GsNtfyParam(general_setting::GsNtfyParam),
+    AudioGetParam(audio::AudioGetParam),
+    AudioRetParam(audio::AudioRetParam),
+    AudioSetParam(audio::AudioSetParam),
+    AudioNtfyParam(audio::AudioNtfyParam),
+    SystemGetStatus(system::SystemGetStatus),
+    SystemRetStatus(system::SystemRetStatus),
+    SystemNtfyStatus(system::SystemNtfyStatus),
+    SystemGetParam(system::SystemGetParam),
+    SystemRetParam(system::SystemRetParam),
+    SystemSetParam(system::SystemSetParam),
+    SystemNtfyParam(system::SystemNtfyParam),
+    SystemGetExtParam(system::SystemGetExtParam),
+    SystemRetExtParam(system::SystemRetExtParam),
+    SystemSetExtParam(system::SystemSetExtParam),
+    SystemNtfyExtParam(system::SystemNtfyExtParam),
+    /// A command this table doesn't know yet, starting with its command byte, so it serializes
+    /// back unchanged
     Unknown(Vec<u8>),
 }
 
+impl Command {
+    pub fn command_type(&self) -> CommandType {
+        match self {
+            Command::ConnectGetProtocolInfo(_) => CommandType::ConnectGetProtocolInfo,
+            Command::ConnectRetProtocolInfo(_) => CommandType::ConnectRetProtocolInfo,
+            Command::CommonGetBatteryLevel(_) => CommandType::CommonGetBatteryLevel,
+            Command::CommonRetBatteryLevel(_) => CommandType::CommonRetBatteryLevel,
+            Command::CommonNtfyBatteryLevel(_) => CommandType::CommonNtfyBatteryLevel,
+            Command::CommonGetUpscalingEffect(_) => CommandType::CommonGetUpscalingEffect,
+            Command::CommonRetUpscalingEffect(_) => CommandType::CommonRetUpscalingEffect,
+            Command::CommonSetUpscalingEffect(_) => CommandType::CommonSetUpscalingEffect,
+            Command::CommonNtfyUpscalingEffect(_) => CommandType::CommonNtfyUpscalingEffect,
+            Command::CommonGetAudioCodec(_) => CommandType::CommonGetAudioCodec,
+            Command::CommonRetAudioCodec(_) => CommandType::CommonRetAudioCodec,
+            Command::CommonNtfyAudioCodec(_) => CommandType::CommonNtfyAudioCodec,
+            Command::CommonSetPowerOff(_) => CommandType::CommonSetPowerOff,
+            Command::VptGetParam(_) => CommandType::VptGetParam,
+            Command::VptRetParam(_) => CommandType::VptRetParam,
+            Command::VptSetParam(_) => CommandType::VptSetParam,
+            Command::VptNtfyParam(_) => CommandType::VptNtfyParam,
+            Command::EqEbbGetParam(_) => CommandType::EqEbbGetParam,
+            Command::EqEbbRetParam(_) => CommandType::EqEbbRetParam,
+            Command::EqEbbSetParam(_) => CommandType::EqEbbSetParam,
+            Command::EqEbbNtfyParam(_) => CommandType::EqEbbNtfyParam,
+            Command::NcAsmGetParam(_) => CommandType::NcAsmGetParam,
+            Command::NcAsmRetParam(_) => CommandType::NcAsmRetParam,
+            Command::NcAsmSetParam(_) => CommandType::NcAsmSetParam,
+            Command::NcAsmNtfyParam(_) => CommandType::NcAsmNtfyParam,
+            Command::OptSetStatus(_) => CommandType::OptSetStatus,
+            Command::OptNtfyStatus(_) => CommandType::OptNtfyStatus,
+            Command::OptGetParam(_) => CommandType::OptGetParam,
+            Command::OptRetParam(_) => CommandType::OptRetParam,
+            Command::OptNtfyParam(_) => CommandType::OptNtfyParam,
+            Command::AlertSetStatus(_) => CommandType::AlertSetStatus,
+            Command::AlertSetParam(_) => CommandType::AlertSetParam,
+            Command::AlertNtfyParam(_) => CommandType::AlertNtfyParam,
+            Command::PlayGetStatus(_) => CommandType::PlayGetStatus,
+            Command::PlayRetStatus(_) => CommandType::PlayRetStatus,
+            Command::PlaySetStatus(_) => CommandType::PlaySetStatus,
+            Command::PlayNtfyStatus(_) => CommandType::PlayNtfyStatus,
+            Command::PlayGetParam(_) => CommandType::PlayGetParam,
+            Command::PlayRetParam(_) => CommandType::PlayRetParam,
+            Command::PlaySetParam(_) => CommandType::PlaySetParam,
+            Command::PlayNtfyParam(_) => CommandType::PlayNtfyParam,
+            Command::FitSetStatus(_) => CommandType::FitSetStatus,
+            Command::FitNtfyStatus(_) => CommandType::FitNtfyStatus,
+            Command::FitGetParam(_) => CommandType::FitGetParam,
+            Command::FitRetParam(_) => CommandType::FitRetParam,
+            Command::FitNtfyParam(_) => CommandType::FitNtfyParam,
+            Command::GsGetCapability(_) => CommandType::GsGetCapability,
+            Command::GsRetCapability(_) => CommandType::GsRetCapability,
+            Command::GsGetParam(_) => CommandType::GsGetParam,
+            Command::GsRetParam(_) => CommandType::GsRetParam,
+            Command::GsSetParam(_) => CommandType::GsSetParam,
+            Command::GsNtfyParam(_) => CommandType::GsNtfyParam,
+            Command::AudioGetParam(_) => CommandType::AudioGetParam,
+            Command::AudioRetParam(_) => CommandType::AudioRetParam,
+            Command::AudioSetParam(_) => CommandType::AudioSetParam,
+            Command::AudioNtfyParam(_) => CommandType::AudioNtfyParam,
+            Command::SystemGetStatus(_) => CommandType::SystemGetStatus,
+            Command::SystemRetStatus(_) => CommandType::SystemRetStatus,
+            Command::SystemNtfyStatus(_) => CommandType::SystemNtfyStatus,
+            Command::SystemGetParam(_) => CommandType::SystemGetParam,
+            Command::SystemRetParam(_) => CommandType::SystemRetParam,
+            Command::SystemSetParam(_) => CommandType::SystemSetParam,
+            Command::SystemNtfyParam(_) => CommandType::SystemNtfyParam,
+            Command::SystemGetExtParam(_) => CommandType::SystemGetExtParam,
+            Command::SystemRetExtParam(_) => CommandType::SystemRetExtParam,
+            Command::SystemSetExtParam(_) => CommandType::SystemSetExtParam,
+            Command::SystemNtfyExtParam(_) => CommandType::SystemNtfyExtParam,
+            Command::Unknown(_) => CommandType::Unknown,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct DataMdr {
     pub command: Command,
@@ -48,34 +264,320 @@ impl ReplCompletion for DataMdr {
 
 impl Serializable for DataMdr {
     fn serialize(&self) -> Vec<u8> {
-        //TODO clean this up a bit to not repeat the match
-        let command_type = match self.command {
-            Command::NcAsmSetParam(_) => CommandType::NcAsmSetParam,
-            Command::NcAsmNtfyParam(_) => CommandType::NcAsmNtfyParam,
-            Command::Unknown(_) => CommandType::Unknown,
-        };
+        self.serialize_for(ProtocolVersion::default())
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_for(bytes, ProtocolVersion::default())
+    }
+
+    fn serialize_for(&self, version: ProtocolVersion) -> Vec<u8> {
         let mut bytes = match &self.command {
-            Command::NcAsmSetParam(x) => x.serialize(),
-            Command::NcAsmNtfyParam(x) => x.serialize(),
-            Command::Unknown(x) => x.clone(),
+            Command::Unknown(x) => return x.clone(),
+            Command::ConnectGetProtocolInfo(x) => x.serialize_for(version),
+            Command::ConnectRetProtocolInfo(x) => x.serialize_for(version),
+            Command::CommonGetBatteryLevel(x) => x.serialize_for(version),
+            Command::CommonRetBatteryLevel(x) => x.serialize_for(version),
+            Command::CommonNtfyBatteryLevel(x) => x.serialize_for(version),
+            Command::CommonGetUpscalingEffect(x) => x.serialize_for(version),
+            Command::CommonRetUpscalingEffect(x) => x.serialize_for(version),
+            Command::CommonSetUpscalingEffect(x) => x.serialize_for(version),
+            Command::CommonNtfyUpscalingEffect(x) => x.serialize_for(version),
+            Command::CommonGetAudioCodec(x) => x.serialize_for(version),
+            Command::CommonRetAudioCodec(x) => x.serialize_for(version),
+            Command::CommonNtfyAudioCodec(x) => x.serialize_for(version),
+            Command::CommonSetPowerOff(x) => x.serialize_for(version),
+            Command::VptGetParam(x) => x.serialize_for(version),
+            Command::VptRetParam(x) => x.serialize_for(version),
+            Command::VptSetParam(x) => x.serialize_for(version),
+            Command::VptNtfyParam(x) => x.serialize_for(version),
+            Command::EqEbbGetParam(x) => x.serialize_for(version),
+            Command::EqEbbRetParam(x) => x.serialize_for(version),
+            Command::EqEbbSetParam(x) => x.serialize_for(version),
+            Command::EqEbbNtfyParam(x) => x.serialize_for(version),
+            Command::NcAsmGetParam(x) => x.serialize_for(version),
+            Command::NcAsmRetParam(x) => x.serialize_for(version),
+            Command::NcAsmSetParam(x) => x.serialize_for(version),
+            Command::NcAsmNtfyParam(x) => x.serialize_for(version),
+            Command::OptSetStatus(x) => x.serialize_for(version),
+            Command::OptNtfyStatus(x) => x.serialize_for(version),
+            Command::OptGetParam(x) => x.serialize_for(version),
+            Command::OptRetParam(x) => x.serialize_for(version),
+            Command::OptNtfyParam(x) => x.serialize_for(version),
+            Command::AlertSetStatus(x) => x.serialize_for(version),
+            Command::AlertSetParam(x) => x.serialize_for(version),
+            Command::AlertNtfyParam(x) => x.serialize_for(version),
+            Command::PlayGetStatus(x) => x.serialize_for(version),
+            Command::PlayRetStatus(x) => x.serialize_for(version),
+            Command::PlaySetStatus(x) => x.serialize_for(version),
+            Command::PlayNtfyStatus(x) => x.serialize_for(version),
+            Command::PlayGetParam(x) => x.serialize_for(version),
+            Command::PlayRetParam(x) => x.serialize_for(version),
+            Command::PlaySetParam(x) => x.serialize_for(version),
+            Command::PlayNtfyParam(x) => x.serialize_for(version),
+            Command::FitSetStatus(x) => x.serialize_for(version),
+            Command::FitNtfyStatus(x) => x.serialize_for(version),
+            Command::FitGetParam(x) => x.serialize_for(version),
+            Command::FitRetParam(x) => x.serialize_for(version),
+            Command::FitNtfyParam(x) => x.serialize_for(version),
+            Command::GsGetCapability(x) => x.serialize_for(version),
+            Command::GsRetCapability(x) => x.serialize_for(version),
+            Command::GsGetParam(x) => x.serialize_for(version),
+            Command::GsRetParam(x) => x.serialize_for(version),
+            Command::GsSetParam(x) => x.serialize_for(version),
+            Command::GsNtfyParam(x) => x.serialize_for(version),
+            Command::AudioGetParam(x) => x.serialize_for(version),
+            Command::AudioRetParam(x) => x.serialize_for(version),
+            Command::AudioSetParam(x) => x.serialize_for(version),
+            Command::AudioNtfyParam(x) => x.serialize_for(version),
+            Command::SystemGetStatus(x) => x.serialize_for(version),
+            Command::SystemRetStatus(x) => x.serialize_for(version),
+            Command::SystemNtfyStatus(x) => x.serialize_for(version),
+            Command::SystemGetParam(x) => x.serialize_for(version),
+            Command::SystemRetParam(x) => x.serialize_for(version),
+            Command::SystemSetParam(x) => x.serialize_for(version),
+            Command::SystemNtfyParam(x) => x.serialize_for(version),
+            Command::SystemGetExtParam(x) => x.serialize_for(version),
+            Command::SystemRetExtParam(x) => x.serialize_for(version),
+            Command::SystemSetExtParam(x) => x.serialize_for(version),
+            Command::SystemNtfyExtParam(x) => x.serialize_for(version),
         };
 
-        let mut ret = vec![command_type.into()];
+        let mut ret = vec![self.command.command_type().into()];
         ret.append(&mut bytes);
         ret
     }
 
-    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+    fn deserialize_for(bytes: &[u8], version: ProtocolVersion) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
         let command_type = bytes[0].into();
         let command = match command_type {
-            CommandType::NcAsmSetParam => {
-                Command::NcAsmSetParam(nc_asm::NcAsmSetParam::deserialize(&bytes[1..])?)
+            CommandType::ConnectGetProtocolInfo => Command::ConnectGetProtocolInfo(
+                connect::ConnectGetProtocolInfo::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::ConnectRetProtocolInfo => Command::ConnectRetProtocolInfo(
+                connect::ConnectRetProtocolInfo::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonGetBatteryLevel => Command::CommonGetBatteryLevel(
+                common::CommonGetBatteryLevel::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonRetBatteryLevel => Command::CommonRetBatteryLevel(
+                common::CommonRetBatteryLevel::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonNtfyBatteryLevel => Command::CommonNtfyBatteryLevel(
+                common::CommonNtfyBatteryLevel::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonGetUpscalingEffect => Command::CommonGetUpscalingEffect(
+                common::CommonGetUpscalingEffect::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonRetUpscalingEffect => Command::CommonRetUpscalingEffect(
+                common::CommonRetUpscalingEffect::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonSetUpscalingEffect => Command::CommonSetUpscalingEffect(
+                common::CommonSetUpscalingEffect::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonNtfyUpscalingEffect => Command::CommonNtfyUpscalingEffect(
+                common::CommonNtfyUpscalingEffect::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonGetAudioCodec => Command::CommonGetAudioCodec(
+                common::CommonGetAudioCodec::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonRetAudioCodec => Command::CommonRetAudioCodec(
+                common::CommonRetAudioCodec::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonNtfyAudioCodec => Command::CommonNtfyAudioCodec(
+                common::CommonNtfyAudioCodec::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::CommonSetPowerOff => Command::CommonSetPowerOff(
+                common::CommonSetPowerOff::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::VptGetParam => {
+                Command::VptGetParam(vpt::VptGetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::VptRetParam => {
+                Command::VptRetParam(vpt::VptRetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::VptSetParam => {
+                Command::VptSetParam(vpt::VptSetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::VptNtfyParam => {
+                Command::VptNtfyParam(vpt::VptNtfyParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::EqEbbGetParam => Command::EqEbbGetParam(
+                eq_ebb::EqEbbGetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::EqEbbRetParam => Command::EqEbbRetParam(
+                eq_ebb::EqEbbRetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::EqEbbSetParam => Command::EqEbbSetParam(
+                eq_ebb::EqEbbSetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::EqEbbNtfyParam => Command::EqEbbNtfyParam(
+                eq_ebb::EqEbbNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::NcAsmGetParam => Command::NcAsmGetParam(
+                nc_asm::NcAsmGetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::NcAsmRetParam => Command::NcAsmRetParam(
+                nc_asm::NcAsmRetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::NcAsmSetParam => Command::NcAsmSetParam(
+                nc_asm::NcAsmSetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::NcAsmNtfyParam => Command::NcAsmNtfyParam(
+                nc_asm::NcAsmNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::OptSetStatus => {
+                Command::OptSetStatus(opt::OptSetStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::OptNtfyStatus => {
+                Command::OptNtfyStatus(opt::OptNtfyStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::OptGetParam => {
+                Command::OptGetParam(opt::OptGetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::OptRetParam => {
+                Command::OptRetParam(opt::OptRetParam::deserialize_for(&bytes[1..], version)?)
             }
-            CommandType::NcAsmNtfyParam => {
-                Command::NcAsmNtfyParam(nc_asm::NcAsmNtfyParam::deserialize(&bytes[1..])?)
+            CommandType::OptNtfyParam => {
+                Command::OptNtfyParam(opt::OptNtfyParam::deserialize_for(&bytes[1..], version)?)
             }
-            CommandType::Unknown => Command::Unknown(bytes[1..].to_vec()),
+            CommandType::AlertSetStatus => Command::AlertSetStatus(
+                alert::AlertSetStatus::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::AlertSetParam => {
+                Command::AlertSetParam(alert::AlertSetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::AlertNtfyParam => Command::AlertNtfyParam(
+                alert::AlertNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::PlayGetStatus => {
+                Command::PlayGetStatus(play::PlayGetStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlayRetStatus => {
+                Command::PlayRetStatus(play::PlayRetStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlaySetStatus => {
+                Command::PlaySetStatus(play::PlaySetStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlayNtfyStatus => Command::PlayNtfyStatus(
+                play::PlayNtfyStatus::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::PlayGetParam => {
+                Command::PlayGetParam(play::PlayGetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlayRetParam => {
+                Command::PlayRetParam(play::PlayRetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlaySetParam => {
+                Command::PlaySetParam(play::PlaySetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::PlayNtfyParam => {
+                Command::PlayNtfyParam(play::PlayNtfyParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::FitSetStatus => {
+                Command::FitSetStatus(fit::FitSetStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::FitNtfyStatus => {
+                Command::FitNtfyStatus(fit::FitNtfyStatus::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::FitGetParam => {
+                Command::FitGetParam(fit::FitGetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::FitRetParam => {
+                Command::FitRetParam(fit::FitRetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::FitNtfyParam => {
+                Command::FitNtfyParam(fit::FitNtfyParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::GsGetCapability => Command::GsGetCapability(
+                general_setting::GsGetCapability::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::GsRetCapability => Command::GsRetCapability(
+                general_setting::GsRetCapability::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::GsGetParam => Command::GsGetParam(
+                general_setting::GsGetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::GsRetParam => Command::GsRetParam(
+                general_setting::GsRetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::GsSetParam => Command::GsSetParam(
+                general_setting::GsSetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::GsNtfyParam => Command::GsNtfyParam(
+                general_setting::GsNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::AudioGetParam => {
+                Command::AudioGetParam(audio::AudioGetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::AudioRetParam => {
+                Command::AudioRetParam(audio::AudioRetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::AudioSetParam => {
+                Command::AudioSetParam(audio::AudioSetParam::deserialize_for(&bytes[1..], version)?)
+            }
+            CommandType::AudioNtfyParam => Command::AudioNtfyParam(
+                audio::AudioNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemGetStatus => Command::SystemGetStatus(
+                system::SystemGetStatus::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemRetStatus => Command::SystemRetStatus(
+                system::SystemRetStatus::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemNtfyStatus => Command::SystemNtfyStatus(
+                system::SystemNtfyStatus::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemGetParam => Command::SystemGetParam(
+                system::SystemGetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemRetParam => Command::SystemRetParam(
+                system::SystemRetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemSetParam => Command::SystemSetParam(
+                system::SystemSetParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemNtfyParam => Command::SystemNtfyParam(
+                system::SystemNtfyParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemGetExtParam => Command::SystemGetExtParam(
+                system::SystemGetExtParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemRetExtParam => Command::SystemRetExtParam(
+                system::SystemRetExtParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemSetExtParam => Command::SystemSetExtParam(
+                system::SystemSetExtParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::SystemNtfyExtParam => Command::SystemNtfyExtParam(
+                system::SystemNtfyExtParam::deserialize_for(&bytes[1..], version)?,
+            ),
+            CommandType::Unknown => Command::Unknown(bytes.to_vec()),
         };
         Ok(Self { command })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_payloads() {
+        assert!(matches!(
+            DataMdr::deserialize(&[]),
+            Err(DeserializeError::InvalidLength(0))
+        ));
+    }
+
+    #[test]
+    fn unknown_commands_keep_their_command_byte() {
+        let bytes = [120, 1, 2, 3];
+        let data_mdr = DataMdr::deserialize(&bytes).unwrap();
+        assert!(matches!(&data_mdr.command, Command::Unknown(x) if x == &bytes));
+        assert_eq!(data_mdr.serialize(), bytes);
+    }
+}
@@ -0,0 +1,173 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AlertInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AlertInquiredType {
+    FixedMessage = 0,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AlertStatusValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AlertStatusValue {
+    Disable = 0,
+    Enable = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AlertMessageType
+///
+/// What the headset is asking about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum AlertMessageType {
+    DisconnectCausedByConnectionModeChange,
+    DisconnectCausedByChangeToLdac,
+    MultipointDisabledBySoundQualityPrior,
+    SpeakToChatDisabledByNcOptimizer,
+    CautionForBatteryCharging,
+    /// A message type that isn't known yet. The alert still has to be answered, or the headset
+    /// keeps waiting.
+    Unknown(u8),
+}
+
+impl From<u8> for AlertMessageType {
+    fn from(x: u8) -> Self {
+        match x {
+            0 => AlertMessageType::DisconnectCausedByConnectionModeChange,
+            1 => AlertMessageType::DisconnectCausedByChangeToLdac,
+            2 => AlertMessageType::MultipointDisabledBySoundQualityPrior,
+            3 => AlertMessageType::SpeakToChatDisabledByNcOptimizer,
+            4 => AlertMessageType::CautionForBatteryCharging,
+            x => AlertMessageType::Unknown(x),
+        }
+    }
+}
+
+impl From<AlertMessageType> for u8 {
+    fn from(x: AlertMessageType) -> Self {
+        match x {
+            AlertMessageType::DisconnectCausedByConnectionModeChange => 0,
+            AlertMessageType::DisconnectCausedByChangeToLdac => 1,
+            AlertMessageType::MultipointDisabledBySoundQualityPrior => 2,
+            AlertMessageType::SpeakToChatDisabledByNcOptimizer => 3,
+            AlertMessageType::CautionForBatteryCharging => 4,
+            AlertMessageType::Unknown(x) => x,
+        }
+    }
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AlertActionType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AlertActionType {
+    /// The alert can be confirmed or rejected
+    PositiveNegative = 0,
+    /// The alert can only be confirmed
+    ConfirmationOnly = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AlertAction
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AlertAction {
+    Negative = 0,
+    Positive = 1,
+}
+
+/// Enables or disables alerts. While they are disabled, the headset applies the change an alert
+/// would have asked about without asking.
+#[derive(Debug, FromRepl)]
+pub struct AlertSetStatus(pub AlertInquiredType, pub AlertStatusValue);
+
+impl Serializable for AlertSetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+/// The answer to an `AlertNtfyParam`
+#[derive(Debug, FromRepl)]
+pub struct AlertSetParam(pub AlertInquiredType, pub AlertMessageType, pub AlertAction);
+
+impl Serializable for AlertSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into(), self.2.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 3 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].into(),
+            bytes[2].try_into()?,
+        ))
+    }
+}
+
+/// Sent when a setting change needs to be confirmed, which is done with an `AlertSetParam`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct AlertNtfyParam(
+    pub AlertInquiredType,
+    pub AlertMessageType,
+    pub AlertActionType,
+);
+
+impl Serializable for AlertNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into(), self.2.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 3 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].into(),
+            bytes[2].try_into()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alert_round_trip() {
+        let bytes = [0, 1, 0];
+        let alert = AlertNtfyParam::deserialize(&bytes).unwrap();
+        assert_eq!(
+            alert,
+            AlertNtfyParam(
+                AlertInquiredType::FixedMessage,
+                AlertMessageType::DisconnectCausedByChangeToLdac,
+                AlertActionType::PositiveNegative
+            )
+        );
+        assert_eq!(alert.serialize(), bytes);
+    }
+
+    #[test]
+    fn unknown_alerts_can_be_answered() {
+        let alert = AlertNtfyParam::deserialize(&[0, 42, 1]).unwrap();
+        assert_eq!(alert.1, AlertMessageType::Unknown(42));
+
+        let answer = AlertSetParam(alert.0, alert.1, AlertAction::Positive);
+        assert_eq!(answer.serialize(), [0, 42, 1]);
+    }
+}
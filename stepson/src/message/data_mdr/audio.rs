@@ -0,0 +1,145 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AudioInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AudioInquiredType {
+    ConnectionMode = 0,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.ConnectionModeSettingValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum ConnectionMode {
+    SoundQualityPrior = 0,
+    ConnectionQualityPrior = 1,
+}
+
+/// The payload shared by `AudioRetParam`, `AudioSetParam` and `AudioNtfyParam`, whose layout
+/// depends on the leading `AudioInquiredType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum AudioParam {
+    ConnectionMode(ConnectionMode),
+}
+
+impl AudioParam {
+    pub fn inquired_type(&self) -> AudioInquiredType {
+        match self {
+            AudioParam::ConnectionMode(_) => AudioInquiredType::ConnectionMode,
+        }
+    }
+}
+
+impl Serializable for AudioParam {
+    fn serialize(&self) -> Vec<u8> {
+        match self {
+            AudioParam::ConnectionMode(x) => vec![self.inquired_type().into(), (*x).into()],
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            AudioInquiredType::ConnectionMode => AudioParam::ConnectionMode(bytes[1].try_into()?),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct AudioGetParam(pub AudioInquiredType);
+
+impl Serializable for AudioGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct AudioRetParam(pub AudioParam);
+
+impl Serializable for AudioRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(AudioParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct AudioSetParam(pub AudioParam);
+
+impl Serializable for AudioSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(AudioParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct AudioNtfyParam(pub AudioParam);
+
+impl Serializable for AudioNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(AudioParam::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_mode_round_trip() {
+        for &(byte, mode) in &[
+            (0, ConnectionMode::SoundQualityPrior),
+            (1, ConnectionMode::ConnectionQualityPrior),
+        ] {
+            let param = AudioNtfyParam::deserialize(&[0, byte]).unwrap();
+            assert_eq!(param.0, AudioParam::ConnectionMode(mode));
+            assert_eq!(param.serialize(), [0, byte]);
+        }
+        assert_eq!(
+            AudioGetParam(AudioInquiredType::ConnectionMode).serialize(),
+            [0]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_connection_modes() {
+        assert!(matches!(
+            AudioParam::deserialize(&[0]),
+            Err(DeserializeError::InvalidLength(1))
+        ));
+        assert!(matches!(
+            AudioParam::deserialize(&[0, 2]),
+            Err(DeserializeError::TryFromPrimitive(2))
+        ));
+        assert!(matches!(
+            AudioParam::deserialize(&[1, 0]),
+            Err(DeserializeError::TryFromPrimitive(1))
+        ));
+    }
+}
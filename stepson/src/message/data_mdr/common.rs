@@ -0,0 +1,489 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.BatteryInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum BatteryInquiredType {
+    Battery = 0,
+    LeftRightBattery = 1,
+    CradleBattery = 2,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.BatteryChargingStatus
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum BatteryChargingStatus {
+    NotCharging = 0,
+    Charging = 1,
+}
+
+/// A battery level in percent, along with whether that battery is charging
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct BatteryLevel(pub u8, pub BatteryChargingStatus);
+
+impl Serializable for BatteryLevel {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0, self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0], bytes[1].try_into()?))
+    }
+}
+
+/// The battery levels of the left and right earbuds of a true wireless headset
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct LeftRightBatteryLevel(pub BatteryLevel, pub BatteryLevel);
+
+/// The payload shared by `CommonRetBatteryLevel` and `CommonNtfyBatteryLevel`, whose layout
+/// depends on the leading `BatteryInquiredType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum BatteryStatus {
+    Battery(BatteryLevel),
+    LeftRightBattery(LeftRightBatteryLevel),
+    CradleBattery(BatteryLevel),
+}
+
+impl BatteryStatus {
+    pub fn inquired_type(&self) -> BatteryInquiredType {
+        match self {
+            BatteryStatus::Battery(_) => BatteryInquiredType::Battery,
+            BatteryStatus::LeftRightBattery(_) => BatteryInquiredType::LeftRightBattery,
+            BatteryStatus::CradleBattery(_) => BatteryInquiredType::CradleBattery,
+        }
+    }
+}
+
+impl Serializable for BatteryStatus {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.inquired_type().into()];
+        match self {
+            BatteryStatus::Battery(x) | BatteryStatus::CradleBattery(x) => {
+                ret.append(&mut x.serialize())
+            }
+            BatteryStatus::LeftRightBattery(LeftRightBatteryLevel(left, right)) => {
+                ret.append(&mut left.serialize());
+                ret.append(&mut right.serialize());
+            }
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let inquired_type = bytes[0].try_into()?;
+        let len = match inquired_type {
+            BatteryInquiredType::LeftRightBattery => 5,
+            _ => 3,
+        };
+        if bytes.len() < len {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match inquired_type {
+            BatteryInquiredType::Battery => {
+                BatteryStatus::Battery(BatteryLevel::deserialize(&bytes[1..])?)
+            }
+            BatteryInquiredType::LeftRightBattery => {
+                BatteryStatus::LeftRightBattery(LeftRightBatteryLevel(
+                    BatteryLevel::deserialize(&bytes[1..3])?,
+                    BatteryLevel::deserialize(&bytes[3..5])?,
+                ))
+            }
+            BatteryInquiredType::CradleBattery => {
+                BatteryStatus::CradleBattery(BatteryLevel::deserialize(&bytes[1..])?)
+            }
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonGetBatteryLevel(pub BatteryInquiredType);
+
+impl Serializable for CommonGetBatteryLevel {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonRetBatteryLevel(pub BatteryStatus);
+
+impl Serializable for CommonRetBatteryLevel {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(BatteryStatus::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonNtfyBatteryLevel(pub BatteryStatus);
+
+impl Serializable for CommonNtfyBatteryLevel {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(BatteryStatus::deserialize(bytes)?))
+    }
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.UpscalingInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum UpscalingInquiredType {
+    Fixed = 0,
+    Automatic = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.UpscalingType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum UpscalingType {
+    DseeHx = 0,
+    Dsee = 1,
+    DseeHxAi = 2,
+    DseeExtreme = 3,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.UpscalingEffect
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum UpscalingEffect {
+    Off = 0,
+    On = 1,
+    Auto = 2,
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonGetUpscalingEffect(pub UpscalingInquiredType);
+
+impl Serializable for CommonGetUpscalingEffect {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonRetUpscalingEffect(
+    pub UpscalingInquiredType,
+    pub UpscalingType,
+    pub UpscalingEffect,
+);
+
+impl Serializable for CommonRetUpscalingEffect {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into(), self.2.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 3 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            bytes[2].try_into()?,
+        ))
+    }
+}
+
+/// The upscaling type is fixed by the model, so only the effect can be changed
+#[derive(Debug, FromRepl)]
+pub struct CommonSetUpscalingEffect(pub UpscalingInquiredType, pub UpscalingEffect);
+
+impl Serializable for CommonSetUpscalingEffect {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonNtfyUpscalingEffect(
+    pub UpscalingInquiredType,
+    pub UpscalingType,
+    pub UpscalingEffect,
+);
+
+impl Serializable for CommonNtfyUpscalingEffect {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into(), self.2.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 3 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            bytes[2].try_into()?,
+        ))
+    }
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AudioCodecInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AudioCodecInquiredType {
+    Fixed = 0,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AudioCodec
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum AudioCodec {
+    Unsettled,
+    Sbc,
+    Aac,
+    Ldac,
+    AptX,
+    AptXHd,
+    Lc3,
+    /// Any other codec, which the headset reports as 255 when it can't name it
+    Other(u8),
+}
+
+impl From<u8> for AudioCodec {
+    fn from(x: u8) -> Self {
+        match x {
+            0 => AudioCodec::Unsettled,
+            1 => AudioCodec::Sbc,
+            2 => AudioCodec::Aac,
+            16 => AudioCodec::Ldac,
+            32 => AudioCodec::AptX,
+            33 => AudioCodec::AptXHd,
+            48 => AudioCodec::Lc3,
+            x => AudioCodec::Other(x),
+        }
+    }
+}
+
+impl From<AudioCodec> for u8 {
+    fn from(x: AudioCodec) -> Self {
+        match x {
+            AudioCodec::Unsettled => 0,
+            AudioCodec::Sbc => 1,
+            AudioCodec::Aac => 2,
+            AudioCodec::Ldac => 16,
+            AudioCodec::AptX => 32,
+            AudioCodec::AptXHd => 33,
+            AudioCodec::Lc3 => 48,
+            AudioCodec::Other(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonGetAudioCodec(pub AudioCodecInquiredType);
+
+impl Serializable for CommonGetAudioCodec {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonRetAudioCodec(pub AudioCodecInquiredType, pub AudioCodec);
+
+impl Serializable for CommonRetAudioCodec {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].into()))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct CommonNtfyAudioCodec(pub AudioCodecInquiredType, pub AudioCodec);
+
+impl Serializable for CommonNtfyAudioCodec {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].into()))
+    }
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PowerOffInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PowerOffInquiredType {
+    Fixed = 0,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PowerOffSettingValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PowerOffSettingValue {
+    PowerOff = 1,
+}
+
+/// Turns the headset off, which ends the connection. `MessageQueue::send` refuses to send this,
+/// use `MessageQueue::power_off` instead.
+#[derive(Debug, FromRepl)]
+pub struct CommonSetPowerOff(pub PowerOffInquiredType, pub PowerOffSettingValue);
+
+impl Serializable for CommonSetPowerOff {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> BatteryStatus {
+        let status = BatteryStatus::deserialize(bytes).unwrap();
+        assert_eq!(status.serialize(), bytes);
+        status
+    }
+
+    #[test]
+    fn battery_round_trip() {
+        assert_eq!(
+            round_trip(&[0, 70, 1]),
+            BatteryStatus::Battery(BatteryLevel(70, BatteryChargingStatus::Charging))
+        );
+        assert_eq!(
+            round_trip(&[1, 40, 0, 55, 1]),
+            BatteryStatus::LeftRightBattery(LeftRightBatteryLevel(
+                BatteryLevel(40, BatteryChargingStatus::NotCharging),
+                BatteryLevel(55, BatteryChargingStatus::Charging),
+            ))
+        );
+        assert_eq!(
+            round_trip(&[2, 100, 0]),
+            BatteryStatus::CradleBattery(BatteryLevel(100, BatteryChargingStatus::NotCharging))
+        );
+        assert_eq!(
+            CommonGetBatteryLevel(BatteryInquiredType::LeftRightBattery).serialize(),
+            [1]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_battery_levels() {
+        for bytes in &[vec![], vec![0, 70], vec![1, 40, 0, 55]] {
+            assert!(matches!(
+                BatteryStatus::deserialize(bytes),
+                Err(DeserializeError::InvalidLength(n)) if n == bytes.len()
+            ));
+        }
+        assert!(matches!(
+            BatteryStatus::deserialize(&[0, 70, 2]),
+            Err(DeserializeError::TryFromPrimitive(2))
+        ));
+    }
+
+    #[test]
+    fn upscaling_round_trip() {
+        let param = CommonRetUpscalingEffect::deserialize(&[0, 3, 2]).unwrap();
+        assert_eq!(param.1, UpscalingType::DseeExtreme);
+        assert_eq!(param.2, UpscalingEffect::Auto);
+        assert_eq!(param.serialize(), [0, 3, 2]);
+
+        let param = CommonNtfyUpscalingEffect::deserialize(&[1, 0, 1]).unwrap();
+        assert_eq!(param.0, UpscalingInquiredType::Automatic);
+        assert_eq!(param.1, UpscalingType::DseeHx);
+        assert_eq!(param.serialize(), [1, 0, 1]);
+
+        let param = CommonSetUpscalingEffect::deserialize(&[0, 0]).unwrap();
+        assert_eq!(param.1, UpscalingEffect::Off);
+        assert_eq!(param.serialize(), [0, 0]);
+    }
+
+    #[test]
+    fn rejects_invalid_upscaling_effects() {
+        assert!(matches!(
+            CommonRetUpscalingEffect::deserialize(&[0, 3]),
+            Err(DeserializeError::InvalidLength(2))
+        ));
+        assert!(matches!(
+            CommonRetUpscalingEffect::deserialize(&[0, 4, 1]),
+            Err(DeserializeError::TryFromPrimitive(4))
+        ));
+    }
+
+    #[test]
+    fn audio_codec_round_trip() {
+        let param = CommonRetAudioCodec::deserialize(&[0, 16]).unwrap();
+        assert_eq!(param.1, AudioCodec::Ldac);
+        assert_eq!(param.serialize(), [0, 16]);
+
+        let param = CommonNtfyAudioCodec::deserialize(&[0, 48]).unwrap();
+        assert_eq!(param.1, AudioCodec::Lc3);
+        assert_eq!(param.serialize(), [0, 48]);
+    }
+
+    #[test]
+    fn unknown_audio_codecs_are_kept() {
+        for &byte in &[3, 255] {
+            let param = CommonNtfyAudioCodec::deserialize(&[0, byte]).unwrap();
+            assert_eq!(param.1, AudioCodec::Other(byte));
+            assert_eq!(param.serialize(), [0, byte]);
+        }
+        assert_eq!(
+            AudioCodec::from_repl(&mut "other 255".split_whitespace()).unwrap(),
+            AudioCodec::Other(255)
+        );
+    }
+}
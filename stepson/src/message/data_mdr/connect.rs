@@ -0,0 +1,60 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, ProtocolVersion, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.ConnectInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum ConnectInquiredType {
+    Fixed = 0,
+}
+
+/// Asks the device which protocol version it speaks. This is the first thing the app sends after
+/// connecting.
+#[derive(Debug, FromRepl)]
+pub struct ConnectGetProtocolInfo(pub ConnectInquiredType);
+
+impl Serializable for ConnectGetProtocolInfo {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+/// The protocol version, as a big endian u32. Devices append which versions of the command
+/// tables they support, which are ignored.
+#[derive(Debug, FromRepl)]
+pub struct ConnectRetProtocolInfo(pub ConnectInquiredType, pub u32);
+
+impl ConnectRetProtocolInfo {
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion(self.1)
+    }
+}
+
+impl Serializable for ConnectRetProtocolInfo {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.extend_from_slice(&self.1.to_be_bytes());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 5 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            u32::from_be_bytes(bytes[1..5].try_into().unwrap()),
+        ))
+    }
+}
@@ -0,0 +1,368 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.EqEbbInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum EqEbbInquiredType {
+    NoUse = 0,
+    PresetEq = 1,
+    Ebb = 2,
+    PresetEqNonCustomizable = 3,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.EqPresetId
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum EqPresetId {
+    Off = 0,
+    Rock = 1,
+    Pop = 2,
+    Jazz = 3,
+    Dance = 4,
+    Edm = 5,
+    RAndBHipHop = 6,
+    Acoustic = 7,
+    Bright = 16,
+    Excited = 17,
+    Mellow = 18,
+    Relaxed = 19,
+    Vocal = 20,
+    TrebleBoost = 21,
+    BassBoost = 22,
+    Speech = 23,
+    Manual = 160,
+    Custom1 = 161,
+    Custom2 = 162,
+}
+
+pub const EQ_BAND_COUNT: usize = 5;
+pub const EQ_LEVEL_MIN: i8 = -10;
+pub const EQ_LEVEL_MAX: i8 = 10;
+
+/// The levels of an equalizer curve, i.e. the five bands from 400Hz to 16kHz followed by Clear
+/// Bass. Every level is between `EQ_LEVEL_MIN` and `EQ_LEVEL_MAX`, which `new` checks.
+///
+/// On the wire, the levels are offset by 10 so that they are never negative, and Clear Bass comes
+/// first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EqBands([i8; EQ_BAND_COUNT], i8);
+
+impl EqBands {
+    /// Returns `None` if any level is out of range
+    pub fn new(bands: [i8; EQ_BAND_COUNT], bass: i8) -> Option<Self> {
+        if bands
+            .iter()
+            .chain(std::iter::once(&bass))
+            .all(|l| (EQ_LEVEL_MIN..=EQ_LEVEL_MAX).contains(l))
+        {
+            Some(Self(bands, bass))
+        } else {
+            None
+        }
+    }
+
+    pub fn bands(&self) -> [i8; EQ_BAND_COUNT] {
+        self.0
+    }
+
+    pub fn bass(&self) -> i8 {
+        self.1
+    }
+
+    fn level_from_wire(byte: u8) -> Result<i8, DeserializeError> {
+        let level = byte as i16 + EQ_LEVEL_MIN as i16;
+        if level < EQ_LEVEL_MIN as i16 || level > EQ_LEVEL_MAX as i16 {
+            return Err(DeserializeError::TryFromPrimitive(byte));
+        }
+        Ok(level as i8)
+    }
+
+    fn level_to_wire(level: i8) -> u8 {
+        (level - EQ_LEVEL_MIN) as u8
+    }
+}
+
+impl Serializable for EqBands {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![(EQ_BAND_COUNT + 1) as u8, Self::level_to_wire(self.1)];
+        ret.extend(self.0.iter().map(|&l| Self::level_to_wire(l)));
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let count = bytes[0] as usize;
+        if count != EQ_BAND_COUNT + 1 || bytes.len() < count + 1 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let mut bands = [0; EQ_BAND_COUNT];
+        for (band, &byte) in bands.iter_mut().zip(&bytes[2..(count + 1)]) {
+            *band = Self::level_from_wire(byte)?;
+        }
+        Ok(Self(bands, Self::level_from_wire(bytes[1])?))
+    }
+}
+
+/// Parses e.g. `-2 0 3 1 0 bass 5`
+impl FromRepl for EqBands {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let mut bands = [0; EQ_BAND_COUNT];
+        for band in bands.iter_mut() {
+            *band = i8::from_repl(words)?;
+        }
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("bass") => {}
+            Some(w) => return Err(ParseError::UnknownArgument(w.to_string())),
+            None => return Err(ParseError::ExpectedArgument),
+        }
+        let bass = i8::from_repl(words)?;
+
+        for &level in bands.iter().chain(std::iter::once(&bass)) {
+            if !(EQ_LEVEL_MIN..=EQ_LEVEL_MAX).contains(&level) {
+                return Err(ParseError::OutOfRange(level.to_string()));
+            }
+        }
+
+        Ok(Self(bands, bass))
+    }
+}
+
+impl ReplCompletion for EqBands {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+/// The levels a headset reports along with its preset. How many there are depends on the model
+/// and on what was inquired: `Ebb` and `PresetEqNonCustomizable` come without any.
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub enum EqCurve {
+    Empty,
+    Bands(EqBands),
+    /// A number of levels that doesn't map onto `EqBands`, as they are on the wire
+    Other(Vec<u8>),
+}
+
+impl Serializable for EqCurve {
+    fn serialize(&self) -> Vec<u8> {
+        match self {
+            EqCurve::Empty => vec![0],
+            EqCurve::Bands(x) => x.serialize(),
+            EqCurve::Other(x) => {
+                let levels = &x[..x.len().min(u8::MAX as usize)];
+                let mut ret = vec![levels.len() as u8];
+                ret.extend_from_slice(levels);
+                ret
+            }
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let count = bytes[0] as usize;
+        if bytes.len() < count + 1 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match count {
+            0 => EqCurve::Empty,
+            c if c == EQ_BAND_COUNT + 1 => EqCurve::Bands(EqBands::deserialize(bytes)?),
+            _ => EqCurve::Other(bytes[1..(count + 1)].to_vec()),
+        })
+    }
+}
+
+/// What to change the equalizer to: either one of the presets, or a custom curve
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum EqSetting {
+    Preset(EqPresetId),
+    Custom(EqBands),
+}
+
+impl Serializable for EqSetting {
+    fn serialize(&self) -> Vec<u8> {
+        match self {
+            // no bands follow a preset, the headset already knows its curve
+            EqSetting::Preset(x) => vec![(*x).into(), 0],
+            EqSetting::Custom(x) => {
+                let mut ret = vec![EqPresetId::Manual.into()];
+                ret.append(&mut x.serialize());
+                ret
+            }
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            EqPresetId::Manual => EqSetting::Custom(EqBands::deserialize(&bytes[1..])?),
+            x => EqSetting::Preset(x),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct EqEbbGetParam(pub EqEbbInquiredType);
+
+impl Serializable for EqEbbGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+/// The active preset along with its curve, which for `EqPresetId::Manual` is the custom curve
+#[derive(Debug, FromRepl)]
+pub struct EqEbbRetParam(pub EqEbbInquiredType, pub EqPresetId, pub EqCurve);
+
+impl Serializable for EqEbbRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into(), self.1.into()];
+        ret.append(&mut self.2.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            EqCurve::deserialize(&bytes[2..])?,
+        ))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct EqEbbSetParam(pub EqEbbInquiredType, pub EqSetting);
+
+impl Serializable for EqEbbSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.append(&mut self.1.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            EqSetting::deserialize(&bytes[1..])?,
+        ))
+    }
+}
+
+/// Sent when the equalizer is changed from another source, e.g. the phone app
+#[derive(Debug, FromRepl)]
+pub struct EqEbbNtfyParam(pub EqEbbInquiredType, pub EqPresetId, pub EqCurve);
+
+impl Serializable for EqEbbNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into(), self.1.into()];
+        ret.append(&mut self.2.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            EqCurve::deserialize(&bytes[2..])?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_curve_round_trip() {
+        let bytes = [1, 160, 6, 15, 8, 10, 13, 11, 10];
+        let param = EqEbbRetParam::deserialize(&bytes).unwrap();
+        assert_eq!(param.0, EqEbbInquiredType::PresetEq);
+        assert_eq!(param.1, EqPresetId::Manual);
+        assert_eq!(
+            param.2,
+            EqCurve::Bands(EqBands::new([-2, 0, 3, 1, 0], 5).unwrap())
+        );
+        assert_eq!(param.serialize(), bytes);
+    }
+
+    #[test]
+    fn curves_without_levels_deserialize() {
+        let param = EqEbbNtfyParam::deserialize(&[2, 0, 0]).unwrap();
+        assert_eq!(param.0, EqEbbInquiredType::Ebb);
+        assert_eq!(param.2, EqCurve::Empty);
+        assert_eq!(param.serialize(), [2, 0, 0]);
+
+        let param = EqEbbRetParam::deserialize(&[3, 1, 0]).unwrap();
+        assert_eq!(param.0, EqEbbInquiredType::PresetEqNonCustomizable);
+        assert_eq!(param.2, EqCurve::Empty);
+    }
+
+    #[test]
+    fn other_band_counts_are_kept() {
+        let bytes = [1, 160, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 20];
+        let param = EqEbbRetParam::deserialize(&bytes).unwrap();
+        assert_eq!(param.2, EqCurve::Other(bytes[3..].to_vec()));
+        assert_eq!(param.serialize(), bytes);
+    }
+
+    #[test]
+    fn rejects_truncated_and_out_of_range_curves() {
+        assert!(matches!(
+            EqEbbRetParam::deserialize(&[1, 160, 6, 10, 10]),
+            Err(DeserializeError::InvalidLength(3))
+        ));
+        assert!(matches!(
+            EqEbbRetParam::deserialize(&[1, 160, 6, 21, 10, 10, 10, 10, 10]),
+            Err(DeserializeError::TryFromPrimitive(21))
+        ));
+    }
+
+    #[test]
+    fn custom_setting_from_repl() {
+        let setting =
+            EqSetting::from_repl(&mut "custom -2 0 3 1 0 bass 5".split_whitespace()).unwrap();
+        assert_eq!(
+            setting,
+            EqSetting::Custom(EqBands::new([-2, 0, 3, 1, 0], 5).unwrap())
+        );
+        assert_eq!(setting.serialize(), [160, 6, 15, 8, 10, 13, 11, 10]);
+
+        assert!(matches!(
+            EqSetting::from_repl(&mut "custom -2 0 11 1 0 bass 5".split_whitespace()),
+            Err(ParseError::OutOfRange(_))
+        ));
+        assert_eq!(
+            EqSetting::from_repl(&mut "preset rock".split_whitespace()).unwrap(),
+            EqSetting::Preset(EqPresetId::Rock)
+        );
+    }
+}
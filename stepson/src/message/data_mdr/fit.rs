@@ -0,0 +1,151 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.FitInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum FitInquiredType {
+    EarpieceFittingDetection = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.FittingDetectionControl
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum FittingDetectionControl {
+    Cancel = 0,
+    Start = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.FittingDetectionStatus
+///
+/// Like the NC optimizer, the test goes back to `NotRunning` without passing through `Finished` if
+/// it was cancelled or failed, e.g. because an earbud was taken out.
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum FittingDetectionStatus {
+    NotRunning = 0,
+    Measuring = 1,
+    Finished = 2,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.EarpieceSealResult
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum EarpieceSeal {
+    NotMeasured = 0,
+    Good = 1,
+    Poor = 2,
+}
+
+/// The seal of the left and right earpieces
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct EarpieceFit(pub EarpieceSeal, pub EarpieceSeal);
+
+impl Serializable for EarpieceFit {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct FitSetStatus(pub FitInquiredType, pub FittingDetectionControl);
+
+impl Serializable for FitSetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct FitNtfyStatus(pub FitInquiredType, pub FittingDetectionStatus);
+
+impl Serializable for FitNtfyStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct FitGetParam(pub FitInquiredType);
+
+impl Serializable for FitGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+/// The result of the last fit test
+#[derive(Debug, FromRepl)]
+pub struct FitRetParam(pub FitInquiredType, pub EarpieceFit);
+
+impl Serializable for FitRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = vec![self.0.into()];
+        bytes.extend(self.1.serialize());
+        bytes
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            EarpieceFit::deserialize(&bytes[1..])?,
+        ))
+    }
+}
+
+/// Sent once a fit test has measured both earpieces
+#[derive(Debug, FromRepl)]
+pub struct FitNtfyParam(pub FitInquiredType, pub EarpieceFit);
+
+impl Serializable for FitNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = vec![self.0.into()];
+        bytes.extend(self.1.serialize());
+        bytes
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            EarpieceFit::deserialize(&bytes[1..])?,
+        ))
+    }
+}
@@ -0,0 +1,399 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.GsInquiredType
+///
+/// General settings are slots whose meaning is described by the device itself, see
+/// `GsCapability`
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, Hash, FromRepl)]
+#[repr(u8)]
+pub enum GsInquiredType {
+    GeneralSetting1 = 209,
+    GeneralSetting2 = 210,
+    GeneralSetting3 = 211,
+    GeneralSetting4 = 212,
+}
+
+impl GsInquiredType {
+    pub const ALL: [GsInquiredType; 4] = [
+        GsInquiredType::GeneralSetting1,
+        GsInquiredType::GeneralSetting2,
+        GsInquiredType::GeneralSetting3,
+        GsInquiredType::GeneralSetting4,
+    ];
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.GsSettingType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum GsSettingType {
+    BooleanType = 0,
+    ListType = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.GsStringFormat
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum GsStringFormat {
+    /// The string is meant to be displayed as is
+    Raw = 0,
+    /// The string is the name of a string the app is expected to have a translation for
+    EnumName = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.GsSettingValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum GsSettingValue {
+    On = 0,
+    Off = 1,
+}
+
+/// A string sent by the device for display, stored as its format followed by a length-prefixed
+/// string
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub struct GsString(pub GsStringFormat, pub String);
+
+impl GsString {
+    /// Deserializes a string from the start of `bytes`, also returning how many bytes it took up
+    fn deserialize_prefix(bytes: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let len = bytes[1] as usize;
+        if bytes.len() < len + 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let string = Self(
+            bytes[0].try_into()?,
+            String::from_utf8_lossy(&bytes[2..(len + 2)]).into_owned(),
+        );
+        Ok((string, len + 2))
+    }
+}
+
+impl Serializable for GsString {
+    fn serialize(&self) -> Vec<u8> {
+        let bytes = &self.1.as_bytes()[..self.1.len().min(u8::MAX as usize)];
+        let mut ret = vec![self.0.into(), bytes.len() as u8];
+        ret.extend_from_slice(bytes);
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_prefix(bytes)?.0)
+    }
+}
+
+/// The values a setting can take
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GsValues {
+    /// `GsSettingValue::On` or `GsSettingValue::Off`
+    Boolean,
+    /// One of the listed elements, set by index
+    List(Vec<GsString>),
+}
+
+impl GsValues {
+    pub fn setting_type(&self) -> GsSettingType {
+        match self {
+            GsValues::Boolean => GsSettingType::BooleanType,
+            GsValues::List(_) => GsSettingType::ListType,
+        }
+    }
+}
+
+impl Serializable for GsValues {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.setting_type().into()];
+        if let GsValues::List(elements) = self {
+            ret.push(elements.len() as u8);
+            for element in elements {
+                ret.append(&mut element.serialize());
+            }
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(match bytes[0].try_into()? {
+            GsSettingType::BooleanType => GsValues::Boolean,
+            GsSettingType::ListType => {
+                if bytes.len() < 2 {
+                    return Err(DeserializeError::InvalidLength(bytes.len()));
+                }
+                let mut elements = vec![];
+                let mut rest = &bytes[2..];
+                for _ in 0..bytes[1] {
+                    let (element, len) = GsString::deserialize_prefix(rest)?;
+                    rest = &rest[len..];
+                    elements.push(element);
+                }
+                GsValues::List(elements)
+            }
+        })
+    }
+}
+
+/// Parses `boolean`, or `list` followed by the elements as pairs of format and string until the
+/// words run out
+impl FromRepl for GsValues {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("boolean") => Ok(GsValues::Boolean),
+            Some(w) if w.eq_ignore_ascii_case("list") => {
+                let words = words.collect::<Vec<_>>();
+                let mut elements = vec![];
+                for chunk in words.chunks(2) {
+                    elements.push(GsString::from_repl(&mut chunk.iter().copied())?);
+                }
+                Ok(GsValues::List(elements))
+            }
+            Some(w) => Err(ParseError::UnknownArgument(w.to_string())),
+            None => Err(ParseError::ExpectedArgument),
+        }
+    }
+}
+
+impl ReplCompletion for GsValues {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::new(vec![
+            ("Boolean".to_string(), CompletionTree::lazy_empty()),
+            ("List".to_string(), CompletionTree::lazy_empty()),
+        ])
+    }
+}
+
+/// The description of a general setting: its subject, a summary of what it does and the values it
+/// can take
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub struct GsCapability(pub GsString, pub GsString, pub GsValues);
+
+impl GsCapability {
+    /// Parses a value for this setting from its display string, or for lists also from its index.
+    /// Strings are compared case-insensitively.
+    pub fn parse_value(&self, s: &str) -> Option<GsParam> {
+        match &self.2 {
+            GsValues::Boolean => {
+                if s.eq_ignore_ascii_case("on") {
+                    Some(GsParam::Boolean(GsSettingValue::On))
+                } else if s.eq_ignore_ascii_case("off") {
+                    Some(GsParam::Boolean(GsSettingValue::Off))
+                } else {
+                    None
+                }
+            }
+            GsValues::List(elements) => elements
+                .iter()
+                .position(|GsString(_, e)| e.eq_ignore_ascii_case(s))
+                .or_else(|| s.parse::<usize>().ok().filter(|i| *i < elements.len()))
+                .map(|i| GsParam::List(i as u8)),
+        }
+    }
+
+    /// The display strings of the values this setting can take
+    pub fn value_names(&self) -> Vec<String> {
+        match &self.2 {
+            GsValues::Boolean => vec!["on".to_string(), "off".to_string()],
+            GsValues::List(elements) => elements.iter().map(|e| e.1.clone()).collect(),
+        }
+    }
+
+    /// The display string of `value`, or `None` if it isn't valid for this setting
+    pub fn value_name(&self, value: GsParam) -> Option<String> {
+        match (&self.2, value) {
+            (GsValues::Boolean, GsParam::Boolean(GsSettingValue::On)) => Some("on".to_string()),
+            (GsValues::Boolean, GsParam::Boolean(GsSettingValue::Off)) => Some("off".to_string()),
+            (GsValues::List(elements), GsParam::List(i)) => {
+                elements.get(i as usize).map(|e| e.1.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Serializable for GsCapability {
+    fn serialize(&self) -> Vec<u8> {
+        // the setting type comes first, since it determines what follows the strings
+        let mut values = self.2.serialize();
+        let mut ret = vec![values.remove(0)];
+        ret.append(&mut self.0.serialize());
+        ret.append(&mut self.1.serialize());
+        ret.append(&mut values);
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let (subject, subject_len) = GsString::deserialize_prefix(&bytes[1..])?;
+        let rest = &bytes[(subject_len + 1)..];
+        let (summary, summary_len) = GsString::deserialize_prefix(rest)?;
+        let mut values = vec![bytes[0]];
+        values.extend_from_slice(&rest[summary_len..]);
+        Ok(Self(subject, summary, GsValues::deserialize(&values)?))
+    }
+}
+
+/// The value of a general setting, whose layout depends on the leading `GsSettingType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum GsParam {
+    Boolean(GsSettingValue),
+    /// The index of the selected element
+    List(u8),
+}
+
+impl GsParam {
+    pub fn setting_type(&self) -> GsSettingType {
+        match self {
+            GsParam::Boolean(_) => GsSettingType::BooleanType,
+            GsParam::List(_) => GsSettingType::ListType,
+        }
+    }
+}
+
+impl Serializable for GsParam {
+    fn serialize(&self) -> Vec<u8> {
+        match self {
+            GsParam::Boolean(x) => vec![self.setting_type().into(), (*x).into()],
+            GsParam::List(x) => vec![self.setting_type().into(), *x],
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            GsSettingType::BooleanType => GsParam::Boolean(bytes[1].try_into()?),
+            GsSettingType::ListType => GsParam::List(bytes[1]),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsGetCapability(pub GsInquiredType);
+
+impl Serializable for GsGetCapability {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsRetCapability(pub GsInquiredType, pub GsCapability);
+
+impl Serializable for GsRetCapability {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.append(&mut self.1.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            GsCapability::deserialize(&bytes[1..])?,
+        ))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsGetParam(pub GsInquiredType);
+
+impl Serializable for GsGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsRetParam(pub GsInquiredType, pub GsParam);
+
+impl Serializable for GsRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.append(&mut self.1.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            GsParam::deserialize(&bytes[1..])?,
+        ))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsSetParam(pub GsInquiredType, pub GsParam);
+
+impl Serializable for GsSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.append(&mut self.1.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            GsParam::deserialize(&bytes[1..])?,
+        ))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct GsNtfyParam(pub GsInquiredType, pub GsParam);
+
+impl Serializable for GsNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into()];
+        ret.append(&mut self.1.serialize());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            GsParam::deserialize(&bytes[1..])?,
+        ))
+    }
+}
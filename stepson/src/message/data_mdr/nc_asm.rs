@@ -1,8 +1,8 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-use crate::repl::{FromRepl, ReplCompletion};
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
 use crate::serializable::{DeserializeError, Serializable};
 
 /// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AsmId
@@ -91,62 +91,197 @@ pub enum NcSettingValue {
     On = 1,
 }
 
-#[derive(Debug, FromRepl)]
-//TODO understand asm level (i.e. the u8)
-pub struct NcAsmSetParam(
-    NcAsmInquiredType,
-    NcAsmEffect,
-    NcAsmSettingType,
-    NcDualSingleValue,
-    AsmSettingType,
-    AsmId,
-    u8,
-);
+// NC/ASM layout:
+// u8: NcAsmInquiredType::NoiseCancellingAndAmbientSoundMode
+// u8: NcAsmEffect
+// u8: NcAsmSettingType::DualSingleOff
+// u8: NcDualSingleValue, ambient sound is used when this is off
+// u8: AsmSettingType::LevelAdjustment
+// u8: AsmId
+// u8: ambient sound level
+//
+// Devices speaking protocol version 2 or later are also sent this layout, since theirs hasn't
+// been verified yet.
 
-impl Serializable for NcAsmSetParam {
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub enum NcAsmSetting {
+    NcAsm(NcAsm),
+    /// Any other inquired type, whose layout isn't known. The bytes start with the inquired type
+    /// and are sent as they are.
+    Raw(Vec<u8>),
+}
+
+/// Noise cancelling together with ambient sound: whether the effect is on, the noise cancelling
+/// mode, with ambient sound being used when it is off, and the ambient sound voice setting and
+/// level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct NcAsm(pub NcAsmEffect, pub NcDualSingleValue, pub AsmId, pub u8);
+
+impl Serializable for NcAsmSetting {
     fn serialize(&self) -> Vec<u8> {
+        let NcAsm(effect, nc, asm_id, level) = match self {
+            Self::NcAsm(x) => *x,
+            Self::Raw(bytes) => return bytes.clone(),
+        };
         vec![
-            self.0.into(),
-            self.1.into(),
-            self.2.into(),
-            self.3.into(),
-            self.4.into(),
-            self.5.into(),
-            self.6.into(),
+            NcAsmInquiredType::NoiseCancellingAndAmbientSoundMode.into(),
+            effect.into(),
+            NcAsmSettingType::DualSingleOff.into(),
+            nc.into(),
+            AsmSettingType::LevelAdjustment.into(),
+            asm_id.into(),
+            level,
         ]
     }
 
-    fn deserialize(_bytes: &[u8]) -> Result<Self, DeserializeError> {
-        unimplemented!()
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        match NcAsmInquiredType::try_from(bytes[0]) {
+            Ok(NcAsmInquiredType::NoiseCancellingAndAmbientSoundMode) => {
+                if bytes.len() < 7 {
+                    return Err(DeserializeError::InvalidLength(bytes.len()));
+                }
+                Ok(Self::NcAsm(NcAsm(
+                    bytes[1].try_into()?,
+                    bytes[3].try_into()?,
+                    bytes[5].try_into()?,
+                    bytes[6],
+                )))
+            }
+            _ => Ok(Self::Raw(bytes.to_vec())),
+        }
+    }
+}
+
+/// Asks for the NC/ASM state
+#[derive(Debug)]
+pub struct NcAsmGetParam {}
+
+impl FromRepl for NcAsmGetParam {
+    fn from_repl<'a, T>(_words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        Ok(Self {})
+    }
+}
+
+impl ReplCompletion for NcAsmGetParam {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl Serializable for NcAsmGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![NcAsmInquiredType::NoiseCancellingAndAmbientSoundMode.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        NcAsmInquiredType::try_from(bytes[0])?;
+        Ok(Self {})
     }
 }
 
 #[derive(Debug, FromRepl)]
-//TODO understand asm level (i.e. the u8)
-pub struct NcAsmNtfyParam(
-    NcAsmInquiredType,
-    NcAsmEffect,
-    NcAsmSettingType,
-    NcDualSingleValue,
-    AsmSettingType,
-    AsmId,
-    u8,
-);
+pub struct NcAsmRetParam(pub NcAsmSetting);
+
+impl Serializable for NcAsmRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(NcAsmSetting::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct NcAsmSetParam(pub NcAsmSetting);
+
+impl Serializable for NcAsmSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(NcAsmSetting::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct NcAsmNtfyParam(pub NcAsmSetting);
 
 impl Serializable for NcAsmNtfyParam {
     fn serialize(&self) -> Vec<u8> {
-        unimplemented!()
+        self.0.serialize()
     }
 
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
-        Ok(Self(
-            bytes[0].try_into()?,
-            bytes[1].try_into()?,
-            bytes[2].try_into()?,
-            bytes[3].try_into()?,
-            bytes[4].try_into()?,
-            bytes[5].try_into()?,
-            bytes[6],
-        ))
+        Ok(Self(NcAsmSetting::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::{Command, DataMdr};
+    use crate::serializable::ProtocolVersion;
+
+    fn round_trip(bytes: &[u8]) -> NcAsmSetting {
+        let setting = NcAsmSetting::deserialize(bytes).unwrap();
+        assert_eq!(setting.serialize(), bytes);
+        setting
+    }
+
+    #[test]
+    fn nc_and_asm_round_trip() {
+        for &nc in &[
+            NcDualSingleValue::Off,
+            NcDualSingleValue::Single,
+            NcDualSingleValue::Dual,
+        ] {
+            let bytes = [2, 1, 2, nc.into(), 1, 1, 15];
+            assert_eq!(
+                round_trip(&bytes),
+                NcAsmSetting::NcAsm(NcAsm(NcAsmEffect::On, nc, AsmId::Voice, 15))
+            );
+        }
+    }
+
+    #[test]
+    fn other_inquired_types_are_kept_raw() {
+        for bytes in &[
+            vec![0],
+            vec![1, 1, 1, 1],
+            vec![3, 1, 1, 0, 10],
+            vec![23, 1, 0, 0, 7],
+        ] {
+            assert_eq!(round_trip(bytes), NcAsmSetting::Raw(bytes.clone()));
+        }
+    }
+
+    #[test]
+    fn every_protocol_version_gets_the_verified_layout() {
+        let command = Command::NcAsmSetParam(NcAsmSetParam(NcAsmSetting::NcAsm(NcAsm(
+            NcAsmEffect::On,
+            NcDualSingleValue::Single,
+            AsmId::Normal,
+            3,
+        ))));
+        let data_mdr = DataMdr { command };
+        for &version in &[
+            ProtocolVersion::V1,
+            ProtocolVersion::V2,
+            ProtocolVersion(0x0301),
+        ] {
+            assert_eq!(data_mdr.serialize_for(version), [104, 2, 1, 2, 1, 1, 0, 3]);
+        }
+        assert_eq!(NcAsmGetParam {}.serialize(), [2]);
     }
 }
@@ -0,0 +1,150 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.OptInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum OptInquiredType {
+    NcOptimizer = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.NcOptimizerControl
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum NcOptimizerControl {
+    Cancel = 0,
+    Start = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.NcOptimizerStatus
+///
+/// The optimizer measures the wearing condition and then the atmospheric pressure, before
+/// analyzing both. It goes back to `NotRunning` without passing through `Finished` if it was
+/// cancelled or failed, e.g. because the headset was taken off.
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum NcOptimizerStatus {
+    NotRunning = 0,
+    WearingCondition = 1,
+    AtmosphericPressure = 2,
+    Analyzing = 16,
+    Finished = 17,
+}
+
+#[derive(Debug, FromRepl)]
+pub struct OptSetStatus(pub OptInquiredType, pub NcOptimizerControl);
+
+impl Serializable for OptSetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct OptNtfyStatus(pub OptInquiredType, pub NcOptimizerStatus);
+
+impl Serializable for OptNtfyStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct OptGetParam(pub OptInquiredType);
+
+impl Serializable for OptGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+/// The last measured atmospheric pressure, in tenths of an atmosphere
+#[derive(Debug, FromRepl)]
+pub struct OptRetParam(pub OptInquiredType, pub u8);
+
+impl Serializable for OptRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1]))
+    }
+}
+
+/// The measured atmospheric pressure, in tenths of an atmosphere
+#[derive(Debug, FromRepl)]
+pub struct OptNtfyParam(pub OptInquiredType, pub u8);
+
+impl Serializable for OptNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nc_optimizer_round_trip() {
+        let status = OptSetStatus::deserialize(&[1, 1]).unwrap();
+        assert_eq!(status.1, NcOptimizerControl::Start);
+        assert_eq!(status.serialize(), [1, 1]);
+
+        let status = OptNtfyStatus::deserialize(&[1, 17]).unwrap();
+        assert_eq!(status.1, NcOptimizerStatus::Finished);
+        assert_eq!(status.serialize(), [1, 17]);
+
+        let param = OptNtfyParam::deserialize(&[1, 9]).unwrap();
+        assert_eq!(param.1, 9);
+        assert_eq!(param.serialize(), [1, 9]);
+    }
+
+    #[test]
+    fn rejects_invalid_nc_optimizer_statuses() {
+        assert!(matches!(
+            OptNtfyStatus::deserialize(&[1, 3]),
+            Err(DeserializeError::TryFromPrimitive(3))
+        ));
+        assert!(matches!(
+            OptNtfyParam::deserialize(&[1]),
+            Err(DeserializeError::InvalidLength(1))
+        ));
+    }
+}
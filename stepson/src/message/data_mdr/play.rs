@@ -0,0 +1,337 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PlayInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PlayInquiredType {
+    PlaybackController = 1,
+    MusicInfo = 2,
+    MusicVolume = 32,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PlaybackStatus
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PlaybackStatus {
+    Unsettled = 0,
+    Playing = 1,
+    Paused = 2,
+    Stopped = 3,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PlaybackControl
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PlaybackControl {
+    Stop = 0,
+    Play = 1,
+    Pause = 2,
+    NextTrack = 3,
+    PreviousTrack = 4,
+    VolumeUp = 5,
+    VolumeDown = 6,
+}
+
+/// The album, artist and title of the track that is playing, any of which may be empty
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub struct TrackInfo(pub String, pub String, pub String);
+
+impl Serializable for TrackInfo {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![];
+        for s in &[&self.0, &self.1, &self.2] {
+            // longer strings are truncated by the headset anyway
+            let bytes = &s.as_bytes()[..s.len().min(u8::MAX as usize)];
+            ret.push(bytes.len() as u8);
+            ret.extend_from_slice(bytes);
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let mut strings = vec![];
+        let mut rest = bytes;
+        for _ in 0..3 {
+            if rest.is_empty() {
+                return Err(DeserializeError::InvalidLength(bytes.len()));
+            }
+            let len = rest[0] as usize;
+            if rest.len() < len + 1 {
+                return Err(DeserializeError::InvalidLength(bytes.len()));
+            }
+            strings.push(String::from_utf8_lossy(&rest[1..(len + 1)]).into_owned());
+            rest = &rest[(len + 1)..];
+        }
+        let title = strings.pop().unwrap();
+        let artist = strings.pop().unwrap();
+        let album = strings.pop().unwrap();
+        Ok(Self(album, artist, title))
+    }
+}
+
+pub const MUSIC_VOLUME_MAX: u8 = 30;
+
+/// Between 0 and `MUSIC_VOLUME_MAX`, which `new` checks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MusicVolume(u8);
+
+impl MusicVolume {
+    /// Returns `None` if `volume` is out of range
+    pub fn new(volume: u8) -> Option<Self> {
+        if volume <= MUSIC_VOLUME_MAX {
+            Some(Self(volume))
+        } else {
+            None
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.0
+    }
+}
+
+impl FromRepl for MusicVolume {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let volume = u8::from_repl(words)?;
+        Self::new(volume).ok_or_else(|| ParseError::OutOfRange(volume.to_string()))
+    }
+}
+
+impl ReplCompletion for MusicVolume {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+/// The payload shared by `PlayRetParam`, `PlaySetParam` and `PlayNtfyParam`, whose layout depends
+/// on the leading `PlayInquiredType`
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub enum PlayParam {
+    /// Can't be set
+    MusicInfo(TrackInfo),
+    MusicVolume(MusicVolume),
+}
+
+impl PlayParam {
+    pub fn inquired_type(&self) -> PlayInquiredType {
+        match self {
+            PlayParam::MusicInfo(_) => PlayInquiredType::MusicInfo,
+            PlayParam::MusicVolume(_) => PlayInquiredType::MusicVolume,
+        }
+    }
+}
+
+impl Serializable for PlayParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.inquired_type().into()];
+        match self {
+            PlayParam::MusicInfo(x) => ret.append(&mut x.serialize()),
+            PlayParam::MusicVolume(x) => ret.push(x.volume()),
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(match bytes[0].try_into()? {
+            PlayInquiredType::MusicInfo => {
+                PlayParam::MusicInfo(TrackInfo::deserialize(&bytes[1..])?)
+            }
+            PlayInquiredType::MusicVolume => {
+                if bytes.len() < 2 {
+                    return Err(DeserializeError::InvalidLength(bytes.len()));
+                }
+                PlayParam::MusicVolume(
+                    MusicVolume::new(bytes[1])
+                        .ok_or(DeserializeError::TryFromPrimitive(bytes[1]))?,
+                )
+            }
+            x => return Err(DeserializeError::TryFromPrimitive(x.into())),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlayGetStatus(pub PlayInquiredType);
+
+impl Serializable for PlayGetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlayRetStatus(pub PlayInquiredType, pub PlaybackStatus);
+
+impl Serializable for PlayRetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlaySetStatus(pub PlayInquiredType, pub PlaybackControl);
+
+impl Serializable for PlaySetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+/// Sent when playback starts, pauses or stops
+#[derive(Debug, FromRepl)]
+pub struct PlayNtfyStatus(pub PlayInquiredType, pub PlaybackStatus);
+
+impl Serializable for PlayNtfyStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into(), self.1.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(bytes[0].try_into()?, bytes[1].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlayGetParam(pub PlayInquiredType);
+
+impl Serializable for PlayGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlayRetParam(pub PlayParam);
+
+impl Serializable for PlayRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(PlayParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PlaySetParam(pub PlayParam);
+
+impl Serializable for PlaySetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(PlayParam::deserialize(bytes)?))
+    }
+}
+
+/// Sent when the track changes, or the volume is changed with the headset's own controls
+#[derive(Debug, FromRepl)]
+pub struct PlayNtfyParam(pub PlayParam);
+
+impl Serializable for PlayNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(PlayParam::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_info_round_trip() {
+        let mut bytes = vec![2, 5];
+        bytes.extend_from_slice(b"Album");
+        bytes.push(6);
+        bytes.extend_from_slice(b"Artist");
+        bytes.push(0);
+        let param = PlayParam::deserialize(&bytes).unwrap();
+        assert_eq!(
+            param,
+            PlayParam::MusicInfo(TrackInfo(
+                "Album".to_string(),
+                "Artist".to_string(),
+                "".to_string()
+            ))
+        );
+        assert_eq!(param.serialize(), bytes);
+    }
+
+    #[test]
+    fn rejects_truncated_track_info() {
+        for bytes in &[vec![5, b'A'], vec![0, 0], vec![1, b'A', 0, 2, b'B']] {
+            assert!(matches!(
+                TrackInfo::deserialize(bytes),
+                Err(DeserializeError::InvalidLength(n)) if n == bytes.len()
+            ));
+        }
+    }
+
+    #[test]
+    fn music_volume_round_trip() {
+        let param = PlayParam::MusicVolume(MusicVolume::new(MUSIC_VOLUME_MAX).unwrap());
+        assert_eq!(param.serialize(), [32, 30]);
+        assert_eq!(PlayParam::deserialize(&[32, 30]).unwrap(), param);
+    }
+
+    #[test]
+    fn rejects_out_of_range_music_volumes() {
+        assert!(MusicVolume::new(MUSIC_VOLUME_MAX + 1).is_none());
+        assert!(matches!(
+            PlayParam::deserialize(&[32, 31]),
+            Err(DeserializeError::TryFromPrimitive(31))
+        ));
+        assert!(matches!(
+            MusicVolume::from_repl(&mut "31".split_whitespace()),
+            Err(ParseError::OutOfRange(_))
+        ));
+    }
+}
@@ -0,0 +1,643 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.SystemInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum SystemInquiredType {
+    PlaybackControlByWearing = 1,
+    WearingStatus = 2,
+    AssignableSettings = 3,
+    AutoPowerOff = 4,
+    TouchPanel = 5,
+    SpeakToChat = 12,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.PlaybackControlByWearingValue
+///
+/// Whether playback pauses when the headset is taken off, and resumes when it is put back on
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PlaybackControlByWearingValue {
+    Off = 0,
+    On = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.WearingStatus
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum WearingStatus {
+    NotWorn = 0,
+    Worn = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AutoPowerOffElements
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AutoPowerOffElement {
+    PowerOffIn5Min = 0,
+    PowerOffIn30Min = 1,
+    PowerOffIn60Min = 2,
+    PowerOffIn180Min = 3,
+    PowerOffWhenRemovedFromEars = 16,
+    PowerOffDisable = 17,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AssignableSettingsKey
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AssignableSettingsKey {
+    LeftSideKey = 0,
+    RightSideKey = 1,
+    CustomKey = 2,
+    CKey = 3,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.AssignableSettingsPreset
+///
+/// What a key does. The `NcAsm` presets are the modes a press of the NC/AMB button
+/// cycles through, so there is no way to express a cycle of less than two modes.
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum AssignableSettingsPreset {
+    NcAsmOff = 0,
+    NcAsm = 1,
+    NcOff = 2,
+    AsmOff = 3,
+    GoogleAssistant = 16,
+    AmazonAlexa = 17,
+    NoFunction = 255,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.TouchPanelValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum TouchPanelValue {
+    Off = 0,
+    On = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.SpeakToChatValue
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum SpeakToChatValue {
+    Off = 0,
+    On = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.DetectSensitivity
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum DetectSensitivity {
+    Auto = 0,
+    High = 1,
+    Low = 2,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.ModeOutTime
+///
+/// How long after the wearer stops talking speak-to-chat ends
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum ModeOutTime {
+    Short = 0,
+    Standard = 1,
+    Long = 2,
+    None = 3,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.SpeakToChatStatus
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum SpeakToChatStatus {
+    Inactive = 0,
+    Active = 1,
+}
+
+/// The payload shared by `SystemRetParam`, `SystemSetParam` and `SystemNtfyParam`, whose layout
+/// depends on the leading `SystemInquiredType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum SystemParam {
+    PlaybackControlByWearing(PlaybackControlByWearingValue),
+    AutoPowerOff(AutoPowerOffElement),
+    TouchPanel(TouchPanelValue),
+    SpeakToChat(SpeakToChatValue),
+}
+
+impl SystemParam {
+    pub fn inquired_type(&self) -> SystemInquiredType {
+        match self {
+            SystemParam::PlaybackControlByWearing(_) => {
+                SystemInquiredType::PlaybackControlByWearing
+            }
+            SystemParam::AutoPowerOff(_) => SystemInquiredType::AutoPowerOff,
+            SystemParam::TouchPanel(_) => SystemInquiredType::TouchPanel,
+            SystemParam::SpeakToChat(_) => SystemInquiredType::SpeakToChat,
+        }
+    }
+}
+
+impl Serializable for SystemParam {
+    fn serialize(&self) -> Vec<u8> {
+        let value = match self {
+            SystemParam::PlaybackControlByWearing(x) => (*x).into(),
+            SystemParam::AutoPowerOff(x) => (*x).into(),
+            SystemParam::TouchPanel(x) => (*x).into(),
+            SystemParam::SpeakToChat(x) => (*x).into(),
+        };
+        vec![self.inquired_type().into(), value]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            SystemInquiredType::PlaybackControlByWearing => {
+                SystemParam::PlaybackControlByWearing(bytes[1].try_into()?)
+            }
+            SystemInquiredType::AutoPowerOff => SystemParam::AutoPowerOff(bytes[1].try_into()?),
+            SystemInquiredType::TouchPanel => SystemParam::TouchPanel(bytes[1].try_into()?),
+            SystemInquiredType::SpeakToChat => SystemParam::SpeakToChat(bytes[1].try_into()?),
+            x => return Err(DeserializeError::TryFromPrimitive(x.into())),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemGetParam(pub SystemInquiredType);
+
+impl Serializable for SystemGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemRetParam(pub SystemParam);
+
+impl Serializable for SystemRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemSetParam(pub SystemParam);
+
+impl Serializable for SystemSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemNtfyParam(pub SystemParam);
+
+impl Serializable for SystemNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemParam::deserialize(bytes)?))
+    }
+}
+
+/// The settings of speak-to-chat, apart from whether it is enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct SpeakToChatConfig(pub DetectSensitivity, pub ModeOutTime);
+
+/// What a single key does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub struct AssignableSetting(pub AssignableSettingsKey, pub AssignableSettingsPreset);
+
+/// The assignment of every key the headset has, prefixed with their count on the wire. Each key
+/// appears once, which `new` checks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssignableSettings(Vec<AssignableSetting>);
+
+impl AssignableSettings {
+    /// Returns `None` if there are no settings, more than 255, or a key is assigned twice
+    pub fn new(settings: Vec<AssignableSetting>) -> Option<Self> {
+        let unique = settings
+            .iter()
+            .enumerate()
+            .all(|(i, x)| settings[..i].iter().all(|y| y.0 != x.0));
+        if !settings.is_empty() && settings.len() <= u8::MAX as usize && unique {
+            Some(Self(settings))
+        } else {
+            None
+        }
+    }
+
+    pub fn settings(&self) -> &[AssignableSetting] {
+        &self.0
+    }
+}
+
+impl Serializable for AssignableSettings {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.len() as u8];
+        for AssignableSetting(key, preset) in &self.0 {
+            ret.push((*key).into());
+            ret.push((*preset).into());
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let count = bytes[0] as usize;
+        if count == 0 || bytes.len() < 1 + 2 * count {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let mut settings: Vec<AssignableSetting> = vec![];
+        for pair in bytes[1..1 + 2 * count].chunks(2) {
+            let key = pair[0].try_into()?;
+            if settings.iter().any(|x| x.0 == key) {
+                return Err(DeserializeError::TryFromPrimitive(pair[0]));
+            }
+            settings.push(AssignableSetting(key, pair[1].try_into()?));
+        }
+        Ok(Self(settings))
+    }
+}
+
+/// Parses pairs of key and preset until the words run out
+impl FromRepl for AssignableSettings {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let words = words.collect::<Vec<_>>();
+        if words.is_empty() {
+            return Err(ParseError::ExpectedArgument);
+        }
+        let mut settings = vec![];
+        for chunk in words.chunks(2) {
+            settings.push(AssignableSetting::from_repl(&mut chunk.iter().copied())?);
+        }
+        Self::new(settings).ok_or_else(|| ParseError::OutOfRange(words.join(" ")))
+    }
+}
+
+impl ReplCompletion for AssignableSettings {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+/// The payload shared by `SystemRetExtParam`, `SystemSetExtParam` and `SystemNtfyExtParam`, whose
+/// layout depends on the leading `SystemInquiredType`
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub enum SystemExtParam {
+    AssignableSettings(AssignableSettings),
+    SpeakToChat(SpeakToChatConfig),
+}
+
+impl SystemExtParam {
+    pub fn inquired_type(&self) -> SystemInquiredType {
+        match self {
+            SystemExtParam::AssignableSettings(_) => SystemInquiredType::AssignableSettings,
+            SystemExtParam::SpeakToChat(_) => SystemInquiredType::SpeakToChat,
+        }
+    }
+}
+
+impl Serializable for SystemExtParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.inquired_type().into()];
+        match self {
+            SystemExtParam::AssignableSettings(x) => ret.append(&mut x.serialize()),
+            SystemExtParam::SpeakToChat(SpeakToChatConfig(sensitivity, mode_out_time)) => {
+                ret.push((*sensitivity).into());
+                ret.push((*mode_out_time).into());
+            }
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(match bytes[0].try_into()? {
+            SystemInquiredType::AssignableSettings => {
+                SystemExtParam::AssignableSettings(AssignableSettings::deserialize(&bytes[1..])?)
+            }
+            SystemInquiredType::SpeakToChat => {
+                if bytes.len() < 3 {
+                    return Err(DeserializeError::InvalidLength(bytes.len()));
+                }
+                SystemExtParam::SpeakToChat(SpeakToChatConfig(
+                    bytes[1].try_into()?,
+                    bytes[2].try_into()?,
+                ))
+            }
+            x => return Err(DeserializeError::TryFromPrimitive(x.into())),
+        })
+    }
+}
+
+/// The payload shared by `SystemRetStatus` and `SystemNtfyStatus`, whose layout depends on the
+/// leading `SystemInquiredType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum SystemStatus {
+    WearingStatus(WearingStatus),
+    SpeakToChat(SpeakToChatStatus),
+}
+
+impl SystemStatus {
+    pub fn inquired_type(&self) -> SystemInquiredType {
+        match self {
+            SystemStatus::WearingStatus(_) => SystemInquiredType::WearingStatus,
+            SystemStatus::SpeakToChat(_) => SystemInquiredType::SpeakToChat,
+        }
+    }
+}
+
+impl Serializable for SystemStatus {
+    fn serialize(&self) -> Vec<u8> {
+        let value = match self {
+            SystemStatus::WearingStatus(x) => (*x).into(),
+            SystemStatus::SpeakToChat(x) => (*x).into(),
+        };
+        vec![self.inquired_type().into(), value]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            SystemInquiredType::WearingStatus => SystemStatus::WearingStatus(bytes[1].try_into()?),
+            SystemInquiredType::SpeakToChat => SystemStatus::SpeakToChat(bytes[1].try_into()?),
+            x => return Err(DeserializeError::TryFromPrimitive(x.into())),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemGetStatus(pub SystemInquiredType);
+
+impl Serializable for SystemGetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemRetStatus(pub SystemStatus);
+
+impl Serializable for SystemRetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemStatus::deserialize(bytes)?))
+    }
+}
+
+/// Sent e.g. when the headset is put on or taken off, or when speak-to-chat triggers because the
+/// wearer started talking, and again when it ends
+#[derive(Debug, FromRepl)]
+pub struct SystemNtfyStatus(pub SystemStatus);
+
+impl Serializable for SystemNtfyStatus {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemStatus::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemGetExtParam(pub SystemInquiredType);
+
+impl Serializable for SystemGetExtParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemRetExtParam(pub SystemExtParam);
+
+impl Serializable for SystemRetExtParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemExtParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemSetExtParam(pub SystemExtParam);
+
+impl Serializable for SystemSetExtParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemExtParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct SystemNtfyExtParam(pub SystemExtParam);
+
+impl Serializable for SystemNtfyExtParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(SystemExtParam::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> SystemParam {
+        let param = SystemParam::deserialize(bytes).unwrap();
+        assert_eq!(param.serialize(), bytes);
+        param
+    }
+
+    #[test]
+    fn auto_power_off_round_trip() {
+        assert_eq!(
+            round_trip(&[4, 1]),
+            SystemParam::AutoPowerOff(AutoPowerOffElement::PowerOffIn30Min)
+        );
+        assert_eq!(
+            round_trip(&[4, 16]),
+            SystemParam::AutoPowerOff(AutoPowerOffElement::PowerOffWhenRemovedFromEars)
+        );
+        assert_eq!(
+            round_trip(&[4, 17]),
+            SystemParam::AutoPowerOff(AutoPowerOffElement::PowerOffDisable)
+        );
+        assert!(matches!(
+            SystemParam::deserialize(&[4, 4]),
+            Err(DeserializeError::TryFromPrimitive(4))
+        ));
+        assert!(matches!(
+            SystemParam::deserialize(&[4]),
+            Err(DeserializeError::InvalidLength(1))
+        ));
+    }
+
+    #[test]
+    fn assignable_settings_round_trip() {
+        let bytes = [3, 2, 0, 1, 1, 16];
+        let param = SystemExtParam::deserialize(&bytes).unwrap();
+        assert_eq!(
+            param,
+            SystemExtParam::AssignableSettings(
+                AssignableSettings::new(vec![
+                    AssignableSetting(
+                        AssignableSettingsKey::LeftSideKey,
+                        AssignableSettingsPreset::NcAsm
+                    ),
+                    AssignableSetting(
+                        AssignableSettingsKey::RightSideKey,
+                        AssignableSettingsPreset::GoogleAssistant
+                    ),
+                ])
+                .unwrap()
+            )
+        );
+        assert_eq!(param.serialize(), bytes);
+    }
+
+    #[test]
+    fn rejects_invalid_assignable_settings() {
+        let setting = AssignableSetting(
+            AssignableSettingsKey::CustomKey,
+            AssignableSettingsPreset::NoFunction,
+        );
+        assert!(AssignableSettings::new(vec![]).is_none());
+        assert!(AssignableSettings::new(vec![setting, setting]).is_none());
+        assert!(AssignableSettings::new(vec![setting; 256]).is_none());
+
+        assert!(matches!(
+            AssignableSettings::deserialize(&[0]),
+            Err(DeserializeError::InvalidLength(1))
+        ));
+        assert!(matches!(
+            AssignableSettings::deserialize(&[2, 2, 255, 2, 16]),
+            Err(DeserializeError::TryFromPrimitive(2))
+        ));
+
+        assert!(matches!(
+            AssignableSettings::from_repl(&mut "".split_whitespace()),
+            Err(ParseError::ExpectedArgument)
+        ));
+        assert!(matches!(
+            AssignableSettings::from_repl(
+                &mut "leftsidekey ncasm leftsidekey nofunction".split_whitespace()
+            ),
+            Err(ParseError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn speak_to_chat_round_trip() {
+        assert_eq!(
+            round_trip(&[12, 1]),
+            SystemParam::SpeakToChat(SpeakToChatValue::On)
+        );
+
+        let param = SystemExtParam::deserialize(&[12, 2, 3]).unwrap();
+        assert_eq!(
+            param,
+            SystemExtParam::SpeakToChat(SpeakToChatConfig(
+                DetectSensitivity::Low,
+                ModeOutTime::None
+            ))
+        );
+        assert_eq!(param.serialize(), [12, 2, 3]);
+        assert!(matches!(
+            SystemExtParam::deserialize(&[12, 2]),
+            Err(DeserializeError::InvalidLength(2))
+        ));
+
+        let status = SystemNtfyStatus::deserialize(&[12, 1]).unwrap();
+        assert_eq!(
+            status.0,
+            SystemStatus::SpeakToChat(SpeakToChatStatus::Active)
+        );
+        assert_eq!(status.serialize(), [12, 1]);
+    }
+
+    #[test]
+    fn wearing_detection_round_trip() {
+        assert_eq!(
+            round_trip(&[1, 0]),
+            SystemParam::PlaybackControlByWearing(PlaybackControlByWearingValue::Off)
+        );
+
+        let status = SystemRetStatus::deserialize(&[2, 1]).unwrap();
+        assert_eq!(status.0, SystemStatus::WearingStatus(WearingStatus::Worn));
+        assert_eq!(status.serialize(), [2, 1]);
+        assert_eq!(
+            SystemGetStatus(SystemInquiredType::WearingStatus).serialize(),
+            [2]
+        );
+
+        // the worn state is only a status, never a setting
+        assert!(matches!(
+            SystemParam::deserialize(&[2, 1]),
+            Err(DeserializeError::TryFromPrimitive(2))
+        ));
+        assert!(matches!(
+            SystemStatus::deserialize(&[2, 2]),
+            Err(DeserializeError::TryFromPrimitive(2))
+        ));
+    }
+}
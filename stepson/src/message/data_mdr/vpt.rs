@@ -0,0 +1,171 @@
+use std::convert::TryInto;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{FromRepl, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.VptInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum VptInquiredType {
+    Vpt = 1,
+    SoundPosition = 2,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.VptPresetId
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum VptPresetId {
+    Off = 0,
+    OutdoorStage = 1,
+    Arena = 2,
+    ConcertHall = 3,
+    Club = 4,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table1.param.SoundPositionPresetId
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum SoundPositionPresetId {
+    Off = 0,
+    FrontLeft = 1,
+    FrontRight = 2,
+    Front = 3,
+    RearLeft = 17,
+    RearRight = 18,
+}
+
+/// The payload shared by `VptRetParam`, `VptSetParam` and `VptNtfyParam`, whose layout depends on
+/// the leading `VptInquiredType`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromRepl)]
+pub enum VptParam {
+    Vpt(VptPresetId),
+    SoundPosition(SoundPositionPresetId),
+}
+
+impl VptParam {
+    pub fn inquired_type(&self) -> VptInquiredType {
+        match self {
+            VptParam::Vpt(_) => VptInquiredType::Vpt,
+            VptParam::SoundPosition(_) => VptInquiredType::SoundPosition,
+        }
+    }
+}
+
+impl Serializable for VptParam {
+    fn serialize(&self) -> Vec<u8> {
+        let value = match self {
+            VptParam::Vpt(x) => (*x).into(),
+            VptParam::SoundPosition(x) => (*x).into(),
+        };
+        vec![self.inquired_type().into(), value]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(match bytes[0].try_into()? {
+            VptInquiredType::Vpt => VptParam::Vpt(bytes[1].try_into()?),
+            VptInquiredType::SoundPosition => VptParam::SoundPosition(bytes[1].try_into()?),
+        })
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct VptGetParam(pub VptInquiredType);
+
+impl Serializable for VptGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct VptRetParam(pub VptParam);
+
+impl Serializable for VptRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(VptParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct VptSetParam(pub VptParam);
+
+impl Serializable for VptSetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(VptParam::deserialize(bytes)?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct VptNtfyParam(pub VptParam);
+
+impl Serializable for VptNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(VptParam::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> VptParam {
+        let param = VptParam::deserialize(bytes).unwrap();
+        assert_eq!(param.serialize(), bytes);
+        param
+    }
+
+    #[test]
+    fn vpt_and_sound_position_round_trip() {
+        assert_eq!(round_trip(&[1, 2]), VptParam::Vpt(VptPresetId::Arena));
+        assert_eq!(round_trip(&[1, 4]), VptParam::Vpt(VptPresetId::Club));
+        assert_eq!(
+            round_trip(&[2, 1]),
+            VptParam::SoundPosition(SoundPositionPresetId::FrontLeft)
+        );
+        assert_eq!(
+            round_trip(&[2, 18]),
+            VptParam::SoundPosition(SoundPositionPresetId::RearRight)
+        );
+        assert_eq!(VptGetParam(VptInquiredType::SoundPosition).serialize(), [2]);
+    }
+
+    #[test]
+    fn rejects_invalid_presets() {
+        assert!(matches!(
+            VptParam::deserialize(&[1, 5]),
+            Err(DeserializeError::TryFromPrimitive(5))
+        ));
+        assert!(matches!(
+            VptParam::deserialize(&[2, 4]),
+            Err(DeserializeError::TryFromPrimitive(4))
+        ));
+        assert!(matches!(
+            VptParam::deserialize(&[1]),
+            Err(DeserializeError::InvalidLength(1))
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+pub mod peripheral;
+pub mod voice_guidance;
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table2.Command
+#[derive(Clone, Copy, Debug, IntoPrimitive, FromPrimitive, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CommandType {
+    PeripheralSetStatus = 52,
+    PeripheralNtfyStatus = 53,
+    PeripheralGetParam = 54,
+    PeripheralRetParam = 55,
+    PeripheralNtfyParam = 57,
+    VoiceGuidanceGetParam = 70,
+    VoiceGuidanceRetParam = 71,
+    VoiceGuidanceSetParam = 72,
+    VoiceGuidanceNtfyParam = 73,
+    #[num_enum(default)]
+    Unknown,
+}
+
+#[derive(Debug, FromRepl)]
+pub enum Command {
+    PeripheralSetStatus(peripheral::PeripheralSetStatus),
+    PeripheralNtfyStatus(peripheral::PeripheralNtfyStatus),
+    PeripheralGetParam(peripheral::PeripheralGetParam),
+    PeripheralRetParam(peripheral::PeripheralRetParam),
+    PeripheralNtfyParam(peripheral::PeripheralNtfyParam),
+    VoiceGuidanceGetParam(voice_guidance::VoiceGuidanceGetParam),
+    VoiceGuidanceRetParam(voice_guidance::VoiceGuidanceRetParam),
+    VoiceGuidanceSetParam(voice_guidance::VoiceGuidanceSetParam),
+    VoiceGuidanceNtfyParam(voice_guidance::VoiceGuidanceNtfyParam),
+    /// A command this table doesn't know yet, starting with its command byte, so it serializes
+    /// back unchanged
+    Unknown(Vec<u8>),
+}
+
+impl Command {
+    pub fn command_type(&self) -> CommandType {
+        match self {
+            Command::PeripheralSetStatus(_) => CommandType::PeripheralSetStatus,
+            Command::PeripheralNtfyStatus(_) => CommandType::PeripheralNtfyStatus,
+            Command::PeripheralGetParam(_) => CommandType::PeripheralGetParam,
+            Command::PeripheralRetParam(_) => CommandType::PeripheralRetParam,
+            Command::PeripheralNtfyParam(_) => CommandType::PeripheralNtfyParam,
+            Command::VoiceGuidanceGetParam(_) => CommandType::VoiceGuidanceGetParam,
+            Command::VoiceGuidanceRetParam(_) => CommandType::VoiceGuidanceRetParam,
+            Command::VoiceGuidanceSetParam(_) => CommandType::VoiceGuidanceSetParam,
+            Command::VoiceGuidanceNtfyParam(_) => CommandType::VoiceGuidanceNtfyParam,
+            Command::Unknown(_) => CommandType::Unknown,
+        }
+    }
+}
+
+/// A message of the second MDR command table, which uses the same framing as `DataMdr` but has
+/// its own command numbering
+#[derive(Debug)]
+pub struct DataMdrNo2 {
+    pub command: Command,
+}
+
+impl FromRepl for DataMdrNo2 {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        Ok(Self {
+            command: Command::from_repl(words)?,
+        })
+    }
+}
+
+impl ReplCompletion for DataMdrNo2 {
+    fn completion_tree() -> CompletionTree {
+        Command::completion_tree()
+    }
+}
+
+impl Serializable for DataMdrNo2 {
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = match &self.command {
+            Command::Unknown(x) => return x.clone(),
+            Command::PeripheralSetStatus(x) => x.serialize(),
+            Command::PeripheralNtfyStatus(x) => x.serialize(),
+            Command::PeripheralGetParam(x) => x.serialize(),
+            Command::PeripheralRetParam(x) => x.serialize(),
+            Command::PeripheralNtfyParam(x) => x.serialize(),
+            Command::VoiceGuidanceGetParam(x) => x.serialize(),
+            Command::VoiceGuidanceRetParam(x) => x.serialize(),
+            Command::VoiceGuidanceSetParam(x) => x.serialize(),
+            Command::VoiceGuidanceNtfyParam(x) => x.serialize(),
+        };
+
+        let mut ret = vec![self.command.command_type().into()];
+        ret.append(&mut bytes);
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let command_type = bytes[0].into();
+        let command = match command_type {
+            CommandType::PeripheralSetStatus => Command::PeripheralSetStatus(
+                peripheral::PeripheralSetStatus::deserialize(&bytes[1..])?,
+            ),
+            CommandType::PeripheralNtfyStatus => Command::PeripheralNtfyStatus(
+                peripheral::PeripheralNtfyStatus::deserialize(&bytes[1..])?,
+            ),
+            CommandType::PeripheralGetParam => Command::PeripheralGetParam(
+                peripheral::PeripheralGetParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::PeripheralRetParam => Command::PeripheralRetParam(
+                peripheral::PeripheralRetParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::PeripheralNtfyParam => Command::PeripheralNtfyParam(
+                peripheral::PeripheralNtfyParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::VoiceGuidanceGetParam => Command::VoiceGuidanceGetParam(
+                voice_guidance::VoiceGuidanceGetParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::VoiceGuidanceRetParam => Command::VoiceGuidanceRetParam(
+                voice_guidance::VoiceGuidanceRetParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::VoiceGuidanceSetParam => Command::VoiceGuidanceSetParam(
+                voice_guidance::VoiceGuidanceSetParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::VoiceGuidanceNtfyParam => Command::VoiceGuidanceNtfyParam(
+                voice_guidance::VoiceGuidanceNtfyParam::deserialize(&bytes[1..])?,
+            ),
+            CommandType::Unknown => Command::Unknown(bytes.to_vec()),
+        };
+        Ok(Self { command })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_payloads() {
+        assert!(matches!(
+            DataMdrNo2::deserialize(&[]),
+            Err(DeserializeError::InvalidLength(0))
+        ));
+    }
+
+    #[test]
+    fn unknown_commands_keep_their_command_byte() {
+        let bytes = [250, 1, 2, 3];
+        let data_mdr_no2 = DataMdrNo2::deserialize(&bytes).unwrap();
+        assert!(matches!(&data_mdr_no2.command, Command::Unknown(x) if x == &bytes));
+        assert_eq!(data_mdr_no2.serialize(), bytes);
+    }
+}
@@ -0,0 +1,380 @@
+use std::convert::TryInto;
+use std::str::FromStr;
+
+use macaddr::MacAddr6;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::repl::{CompletionTree, FromRepl, ParseError, ReplCompletion};
+use crate::serializable::{DeserializeError, Serializable};
+
+/// The headset sends MAC addresses as text, e.g. `AA:BB:CC:DD:EE:FF`
+const MAC_ADDRESS_LEN: usize = 17;
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table2.param.PeripheralInquiredType
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PeripheralInquiredType {
+    PairingDeviceManagement = 0,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table2.param.ConnectionStatus
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum ConnectionStatus {
+    Disconnected = 0,
+    Connected = 1,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table2.param.PeripheralAction
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PeripheralAction {
+    Connect = 0,
+    Disconnect = 1,
+    Unpair = 2,
+}
+
+/// com.sony.songpal.tandemfamily.message.mdr.v1.table2.param.PeripheralResult
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive, PartialEq, Eq, FromRepl)]
+#[repr(u8)]
+pub enum PeripheralResult {
+    Success = 0,
+    Failure = 1,
+}
+
+fn serialize_mac_address(addr: &MacAddr6) -> Vec<u8> {
+    addr.to_string().into_bytes()
+}
+
+fn deserialize_mac_address(bytes: &[u8]) -> Result<MacAddr6, DeserializeError> {
+    if bytes.len() < MAC_ADDRESS_LEN {
+        return Err(DeserializeError::InvalidLength(bytes.len()));
+    }
+    std::str::from_utf8(&bytes[..MAC_ADDRESS_LEN])
+        .ok()
+        .and_then(|s| MacAddr6::from_str(s).ok())
+        .ok_or(DeserializeError::InvalidMacAddress)
+}
+
+/// A device the headset remembers: its MAC address, whether it is connected and its name. Only
+/// the first 255 bytes of the name are sent.
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub struct PairedDevice(pub MacAddr6, pub ConnectionStatus, pub String);
+
+impl PairedDevice {
+    /// Deserializes a device from the start of `bytes`, also returning how many bytes it took up
+    fn deserialize_prefix(bytes: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        if bytes.len() < MAC_ADDRESS_LEN + 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let name_len = bytes[MAC_ADDRESS_LEN + 1] as usize;
+        let name_start = MAC_ADDRESS_LEN + 2;
+        if bytes.len() < name_start + name_len {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        let device = Self(
+            deserialize_mac_address(bytes)?,
+            bytes[MAC_ADDRESS_LEN].try_into()?,
+            String::from_utf8_lossy(&bytes[name_start..(name_start + name_len)]).into_owned(),
+        );
+        Ok((device, name_start + name_len))
+    }
+}
+
+impl Serializable for PairedDevice {
+    fn serialize(&self) -> Vec<u8> {
+        let name = &self.2.as_bytes()[..self.2.len().min(u8::MAX as usize)];
+        let mut ret = serialize_mac_address(&self.0);
+        ret.push(self.1.into());
+        ret.push(name.len() as u8);
+        ret.extend_from_slice(name);
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self::deserialize_prefix(bytes)?.0)
+    }
+}
+
+/// The devices the headset remembers, prefixed with their count on the wire. Only the first 255
+/// devices are sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PairedDevices(pub Vec<PairedDevice>);
+
+impl Serializable for PairedDevices {
+    fn serialize(&self) -> Vec<u8> {
+        let devices = &self.0[..self.0.len().min(u8::MAX as usize)];
+        let mut ret = vec![devices.len() as u8];
+        for device in devices {
+            ret.append(&mut device.serialize());
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        let mut devices = vec![];
+        let mut rest = &bytes[1..];
+        for _ in 0..bytes[0] {
+            let (device, len) = PairedDevice::deserialize_prefix(rest).map_err(|e| match e {
+                // report the length of the whole list rather than of what was left of it
+                DeserializeError::InvalidLength(_) => DeserializeError::InvalidLength(bytes.len()),
+                e => e,
+            })?;
+            rest = &rest[len..];
+            devices.push(device);
+        }
+        Ok(Self(devices))
+    }
+}
+
+/// Parses devices until the words run out
+impl FromRepl for PairedDevices {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let words = words.collect::<Vec<_>>();
+        let mut devices = vec![];
+        for chunk in words.chunks(3) {
+            let device = PairedDevice::from_repl(&mut chunk.iter().copied())?;
+            if device.2.len() > u8::MAX as usize {
+                return Err(ParseError::OutOfRange(format!(
+                    "name of {} bytes",
+                    device.2.len()
+                )));
+            }
+            devices.push(device);
+        }
+        if devices.len() > u8::MAX as usize {
+            return Err(ParseError::OutOfRange(format!("{} devices", devices.len())));
+        }
+        Ok(Self(devices))
+    }
+}
+
+impl ReplCompletion for PairedDevices {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+/// The payload shared by `PeripheralRetParam` and `PeripheralNtfyParam`, whose layout depends on
+/// the leading `PeripheralInquiredType`
+#[derive(Clone, Debug, PartialEq, Eq, FromRepl)]
+pub enum PeripheralParam {
+    PairingDeviceManagement(PairedDevices),
+}
+
+impl PeripheralParam {
+    pub fn inquired_type(&self) -> PeripheralInquiredType {
+        match self {
+            PeripheralParam::PairingDeviceManagement(_) => {
+                PeripheralInquiredType::PairingDeviceManagement
+            }
+        }
+    }
+}
+
+impl Serializable for PeripheralParam {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.inquired_type().into()];
+        match self {
+            PeripheralParam::PairingDeviceManagement(x) => ret.append(&mut x.serialize()),
+        }
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(match bytes[0].try_into()? {
+            PeripheralInquiredType::PairingDeviceManagement => {
+                PeripheralParam::PairingDeviceManagement(PairedDevices::deserialize(&bytes[1..])?)
+            }
+        })
+    }
+}
+
+/// Connects, disconnects or unpairs the device with the given MAC address
+#[derive(Debug, FromRepl)]
+pub struct PeripheralSetStatus(
+    pub PeripheralInquiredType,
+    pub PeripheralAction,
+    pub MacAddr6,
+);
+
+impl Serializable for PeripheralSetStatus {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into(), self.1.into()];
+        ret.append(&mut serialize_mac_address(&self.2));
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < MAC_ADDRESS_LEN + 2 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            deserialize_mac_address(&bytes[2..])?,
+        ))
+    }
+}
+
+/// The outcome of a `PeripheralSetStatus`
+#[derive(Debug, FromRepl)]
+pub struct PeripheralNtfyStatus(
+    pub PeripheralInquiredType,
+    pub PeripheralAction,
+    pub MacAddr6,
+    pub PeripheralResult,
+);
+
+impl Serializable for PeripheralNtfyStatus {
+    fn serialize(&self) -> Vec<u8> {
+        let mut ret = vec![self.0.into(), self.1.into()];
+        ret.append(&mut serialize_mac_address(&self.2));
+        ret.push(self.3.into());
+        ret
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < MAC_ADDRESS_LEN + 3 {
+            return Err(DeserializeError::InvalidLength(bytes.len()));
+        }
+        Ok(Self(
+            bytes[0].try_into()?,
+            bytes[1].try_into()?,
+            deserialize_mac_address(&bytes[2..])?,
+            bytes[MAC_ADDRESS_LEN + 2].try_into()?,
+        ))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PeripheralGetParam(pub PeripheralInquiredType);
+
+impl Serializable for PeripheralGetParam {
+    fn serialize(&self) -> Vec<u8> {
+        vec![self.0.into()]
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.is_empty() {
+            return Err(DeserializeError::InvalidLength(0));
+        }
+        Ok(Self(bytes[0].try_into()?))
+    }
+}
+
+#[derive(Debug, FromRepl)]
+pub struct PeripheralRetParam(pub PeripheralParam);
+
+impl Serializable for PeripheralRetParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(PeripheralParam::deserialize(bytes)?))
+    }
+}
+
+/// Sent when a device is paired, unpaired, connected or disconnected
+#[derive(Debug, FromRepl)]
+pub struct PeripheralNtfyParam(pub PeripheralParam);
+
+impl Serializable for PeripheralNtfyParam {
+    fn serialize(&self) -> Vec<u8> {
+        self.0.serialize()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self(PeripheralParam::deserialize(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: MacAddr6 = MacAddr6::new(0x00, 0x1a, 0x7d, 0xda, 0x71, 0x13);
+
+    fn device_bytes(status: u8, name: &str) -> Vec<u8> {
+        let mut bytes = b"00:1A:7D:DA:71:13".to_vec();
+        bytes.push(status);
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn paired_devices_round_trip() {
+        let mut bytes = vec![0, 2];
+        bytes.append(&mut device_bytes(1, "Phone"));
+        bytes.append(&mut device_bytes(0, ""));
+        let param = PeripheralParam::deserialize(&bytes).unwrap();
+        assert_eq!(
+            param,
+            PeripheralParam::PairingDeviceManagement(PairedDevices(vec![
+                PairedDevice(MAC, ConnectionStatus::Connected, "Phone".to_string()),
+                PairedDevice(MAC, ConnectionStatus::Disconnected, "".to_string()),
+            ]))
+        );
+        assert_eq!(param.serialize(), bytes);
+    }
+
+    #[test]
+    fn set_and_ntfy_status_round_trip() {
+        let mut bytes = vec![0, 2];
+        bytes.extend_from_slice(b"00:1A:7D:DA:71:13");
+        let status = PeripheralSetStatus::deserialize(&bytes).unwrap();
+        assert_eq!(status.1, PeripheralAction::Unpair);
+        assert_eq!(status.2, MAC);
+        assert_eq!(status.serialize(), bytes);
+
+        bytes.push(1);
+        let status = PeripheralNtfyStatus::deserialize(&bytes).unwrap();
+        assert_eq!(status.3, PeripheralResult::Failure);
+        assert_eq!(status.serialize(), bytes);
+    }
+
+    #[test]
+    fn rejects_truncated_devices() {
+        let mut bytes = vec![1];
+        bytes.append(&mut device_bytes(1, "Phone"));
+        bytes.pop();
+        assert!(matches!(
+            PairedDevices::deserialize(&bytes),
+            Err(DeserializeError::InvalidLength(n)) if n == bytes.len()
+        ));
+
+        // the second device is missing
+        let mut bytes = vec![2];
+        bytes.append(&mut device_bytes(1, "Phone"));
+        assert!(matches!(
+            PairedDevices::deserialize(&bytes),
+            Err(DeserializeError::InvalidLength(n)) if n == bytes.len()
+        ));
+
+        assert!(matches!(
+            PeripheralSetStatus::deserialize(&[0, 2, b'0']),
+            Err(DeserializeError::InvalidLength(3))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_mac_addresses() {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(b"00:1A:7D:DA:71:1G");
+        assert!(matches!(
+            PeripheralSetStatus::deserialize(&bytes),
+            Err(DeserializeError::InvalidMacAddress)
+        ));
+    }
+}
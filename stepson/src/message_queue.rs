@@ -1,19 +1,122 @@
-use anyhow::Result;
+mod traffic;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use thiserror::Error;
 
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::io::{BufReader, ReadHalf, WriteHalf};
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Instant};
 
 use crate::message;
-use crate::message::Message;
-use crate::serializable::Serializable;
+use crate::message::ack::Ack;
+use crate::message::data_mdr::alert::{
+    AlertAction, AlertActionType, AlertNtfyParam, AlertSetParam,
+};
+use crate::message::data_mdr::common::{
+    CommonSetPowerOff, PowerOffInquiredType, PowerOffSettingValue,
+};
+use crate::message::data_mdr::connect::{ConnectGetProtocolInfo, ConnectInquiredType};
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message};
+use crate::serializable::{ProtocolVersion, Serializable};
+
+use traffic::Traffic;
+pub use traffic::TrafficRates;
+
+/// How long to wait for the device to acknowledge a message before sending it again
+const ACK_TIMEOUT: Duration = Duration::from_millis(500);
+/// How many times an unacknowledged message is sent again before giving up
+const ACK_RETRIES: u32 = 2;
+/// How long to wait for the device to report its protocol version
+const PROTOCOL_INFO_TIMEOUT: Duration = Duration::from_secs(5);
 
 type MessageReturnError = (Message, oneshot::Sender<Result<()>>);
 
+/// Called from the recv loop for every alert the device sends, with a responder to answer it
+/// with. The device holds back the change it is asking about until it has been answered.
+pub type AlertCallback = Arc<dyn Fn(AlertNtfyParam, AlertResponder) + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct AlertCallbackSlot(Arc<Mutex<Option<AlertCallback>>>);
+
+impl AlertCallbackSlot {
+    fn set(&self, callback: AlertCallback) {
+        *self.0.lock().unwrap() = Some(callback);
+    }
+
+    /// Taken out of the lock, so that the callback may replace itself
+    fn get(&self) -> Option<AlertCallback> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Hands alerts to the callback from the recv loop, with responders that answer them through a
+/// channel of their own. That way unanswered alerts don't keep the send loop running once the
+/// queue is gone.
+struct AlertDispatcher {
+    callback: AlertCallbackSlot,
+    response_sender: mpsc::UnboundedSender<MessageReturnError>,
+}
+
+impl AlertDispatcher {
+    fn dispatch(&self, alert: AlertNtfyParam) {
+        if let Some(callback) = self.callback.get() {
+            let responder = AlertResponder {
+                alert,
+                alert_response_sender: self.response_sender.clone(),
+            };
+            callback(alert, responder);
+        }
+    }
+}
+
+impl fmt::Debug for AlertCallbackSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let set = self.0.lock().unwrap().is_some();
+        f.debug_tuple("AlertCallbackSlot").field(&set).finish()
+    }
+}
+
+/// Passed from the recv loop to the send loop, which owns the sequence numbers
+#[derive(Debug)]
+enum AckEvent {
+    /// the device acknowledged a message, with the sequence number it expects next
+    Received(u8),
+    /// the device sent a message with this sequence number that needs to be acknowledged
+    Send(u8),
+}
+
+#[derive(Debug, Error)]
+pub enum MessageQueueError {
+    #[error("refusing to send an unconfirmed power off, use `power_off` instead")]
+    UnconfirmedPowerOff,
+    #[error("alert {0:?} can only be confirmed")]
+    AlertNotRejectable(AlertNtfyParam),
+    #[error("the device did not acknowledge the message")]
+    NoAck,
+}
+
 #[derive(Debug)]
 pub struct MessageQueue {
     recv_loop_receiver: mpsc::UnboundedReceiver<Result<Message>>,
+    /// received while waiting for something else in `recv_matching`, returned by `recv` first
+    set_aside: VecDeque<Result<Message>>,
     send_loop_sender: mpsc::UnboundedSender<MessageReturnError>,
+    /// set once the device has been told to power off, so that the recv loop knows that the
+    /// stream closing is not an error
+    disconnect_expected: Arc<AtomicBool>,
+    alert_callback: AlertCallbackSlot,
+    /// the version messages are (de)serialized for, updated when the device reports it
+    protocol_version: Arc<AtomicU32>,
+    traffic: Arc<Mutex<Traffic>>,
 }
 
 impl MessageQueue {
@@ -26,36 +129,153 @@ impl MessageQueue {
         let (recv_loop_sender, recv_loop_receiver) = mpsc::unbounded_channel::<Result<Message>>();
         let (send_loop_sender, send_loop_receiver) =
             mpsc::unbounded_channel::<MessageReturnError>();
+        let disconnect_expected = Arc::new(AtomicBool::new(false));
+
+        let alert_callback = AlertCallbackSlot::default();
+        let (alert_response_sender, alert_response_receiver) =
+            mpsc::unbounded_channel::<MessageReturnError>();
+        let protocol_version = Arc::new(AtomicU32::new(ProtocolVersion::default().0));
+        let traffic = Arc::new(Mutex::new(Traffic::default()));
+        let (ack_sender, ack_receiver) = mpsc::unbounded_channel::<AckEvent>();
 
+        let recv_disconnect_expected = disconnect_expected.clone();
+        let alert_dispatcher = AlertDispatcher {
+            callback: alert_callback.clone(),
+            response_sender: alert_response_sender,
+        };
+        let recv_protocol_version = protocol_version.clone();
+        let recv_traffic = traffic.clone();
         tokio::task::spawn(async move {
-            recv_loop(read_stream, recv_loop_sender).await;
+            recv_loop(
+                read_stream,
+                recv_loop_sender,
+                ack_sender,
+                recv_disconnect_expected,
+                alert_dispatcher,
+                recv_protocol_version,
+                recv_traffic,
+            )
+            .await;
         });
 
+        let send_protocol_version = protocol_version.clone();
+        let send_traffic = traffic.clone();
         tokio::spawn(async move {
-            send_loop(write_stream, send_loop_receiver).await;
+            send_loop(
+                write_stream,
+                send_loop_receiver,
+                alert_response_receiver,
+                ack_receiver,
+                send_protocol_version,
+                send_traffic,
+            )
+            .await;
         });
 
         Self {
             recv_loop_receiver,
+            set_aside: VecDeque::new(),
             send_loop_sender,
+            disconnect_expected,
+            alert_callback,
+            protocol_version,
+            traffic,
         }
     }
 
     pub async fn recv(&mut self) -> Option<Result<Message>> {
-        recv_priv(&mut self.recv_loop_receiver).await
+        recv_priv(&mut self.recv_loop_receiver, &mut self.set_aside).await
+    }
+
+    /// Waits for the first received message `f` returns something for. This is how operations
+    /// that need the queue to themselves, like `NcOptimizer`, wait for the device: every other
+    /// message, and every error short of the connection closing, is set aside and returned by
+    /// `recv` afterwards instead of being lost. Returns `None` once the connection has closed.
+    pub async fn recv_matching<T, F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&Message) -> Option<T>,
+    {
+        recv_matching_priv(&mut self.recv_loop_receiver, &mut self.set_aside, f).await
+    }
+
+    /// A handle for sending while the queue itself is borrowed, e.g. to cancel an operation
+    pub fn sender(&self) -> MessageSender {
+        MessageSender {
+            send_loop_sender: self.send_loop_sender.clone(),
+        }
     }
 
+    /// Sends `message`, waiting until the device has acknowledged it. Shots aren't acknowledged,
+    /// so they return as soon as they have been written. Messages that are acknowledged are
+    /// numbered by the queue, so their `sequence_number` is ignored.
     pub async fn send(&self, message: Message) -> Result<()> {
         send_priv(&self.send_loop_sender, message).await
     }
 
+    /// Turns the device off. Once the device has closed the connection, `recv` returns `None`
+    /// rather than an error.
+    pub async fn power_off(&self) -> Result<()> {
+        power_off_priv(&self.send_loop_sender, &self.disconnect_expected).await
+    }
+
+    /// Sets the callback alerts are passed to, replacing any previous one. Alerts are still
+    /// received through `recv` as well. The device only sends alerts once they have been enabled
+    /// with an `AlertSetStatus`; until then it applies changes without asking.
+    pub fn set_alert_callback<F>(&self, callback: F)
+    where
+        F: Fn(AlertNtfyParam, AlertResponder) + Send + Sync + 'static,
+    {
+        self.alert_callback.set(Arc::new(callback));
+    }
+
+    /// The version messages are (de)serialized for. It is updated whenever the device answers a
+    /// `ConnectGetProtocolInfo`.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion(self.protocol_version.load(Ordering::SeqCst))
+    }
+
+    /// Asks the device for its protocol version and waits for the answer. This should be the
+    /// first thing sent after connecting, since anything sent before is serialized for
+    /// `ProtocolVersion::default`. Anything else received meanwhile is set aside as described for
+    /// `recv_matching`.
+    pub async fn negotiate_protocol_version(&mut self) -> Result<ProtocolVersion> {
+        self.send(Message::data_mdr(Command::ConnectGetProtocolInfo(
+            ConnectGetProtocolInfo(ConnectInquiredType::Fixed),
+        )))
+        .await?;
+
+        let version = self.recv_matching(|message| match &message.data {
+            Data::DataMdr(DataMdr {
+                command: Command::ConnectRetProtocolInfo(info),
+            }) => Some(info.protocol_version()),
+            _ => None,
+        });
+        match tokio::time::timeout(PROTOCOL_INFO_TIMEOUT, version).await {
+            // the recv loop has already switched to it
+            Ok(Some(version)) => Ok(version),
+            Ok(None) => Err(anyhow!("the connection closed before the device answered")),
+            Err(_) => Err(anyhow!(
+                "the device did not report its protocol version in time"
+            )),
+        }
+    }
+
+    pub fn traffic_rates(&self) -> TrafficRates {
+        self.traffic.lock().unwrap().rates()
+    }
+
     pub fn split(self) -> (RecvHalf, SendHalf) {
         (
             RecvHalf {
                 recv_loop_receiver: self.recv_loop_receiver,
+                set_aside: self.set_aside,
             },
             SendHalf {
                 send_loop_sender: self.send_loop_sender,
+                disconnect_expected: self.disconnect_expected,
+                alert_callback: self.alert_callback,
+                protocol_version: self.protocol_version,
+                traffic: self.traffic,
             },
         )
     }
@@ -63,38 +283,164 @@ impl MessageQueue {
 
 async fn recv_priv(
     recv_loop_receiver: &mut mpsc::UnboundedReceiver<Result<Message>>,
+    set_aside: &mut VecDeque<Result<Message>>,
 ) -> Option<Result<Message>> {
-    recv_loop_receiver.recv().await
+    match set_aside.pop_front() {
+        Some(res) => Some(res),
+        None => recv_loop_receiver.recv().await,
+    }
+}
+
+async fn recv_matching_priv<T, F>(
+    recv_loop_receiver: &mut mpsc::UnboundedReceiver<Result<Message>>,
+    set_aside: &mut VecDeque<Result<Message>>,
+    mut f: F,
+) -> Option<T>
+where
+    F: FnMut(&Message) -> Option<T>,
+{
+    loop {
+        let res = recv_loop_receiver.recv().await?;
+        if let Ok(message) = &res {
+            if let Some(x) = f(message) {
+                return Some(x);
+            }
+        }
+        set_aside.push_back(res);
+    }
 }
 
 async fn send_priv(
     send_loop_sender: &mpsc::UnboundedSender<MessageReturnError>,
     message: Message,
 ) -> Result<()> {
+    if let Data::DataMdr(DataMdr {
+        command: Command::CommonSetPowerOff(_),
+    })
+    | Data::ShotMdr(DataMdr {
+        command: Command::CommonSetPowerOff(_),
+    }) = message.data
+    {
+        return Err(MessageQueueError::UnconfirmedPowerOff.into());
+    }
+    send_unchecked(send_loop_sender, message).await
+}
+
+async fn power_off_priv(
+    send_loop_sender: &mpsc::UnboundedSender<MessageReturnError>,
+    disconnect_expected: &AtomicBool,
+) -> Result<()> {
+    let message = Message {
+        sequence_number: 0,
+        data: Data::DataMdr(DataMdr {
+            command: Command::CommonSetPowerOff(CommonSetPowerOff(
+                PowerOffInquiredType::Fixed,
+                PowerOffSettingValue::PowerOff,
+            )),
+        }),
+    };
+
+    disconnect_expected.store(true, Ordering::SeqCst);
     let (tx, rx) = oneshot::channel();
-    let full = (message, tx);
-    send_loop_sender.send(full)?;
-    if let Err(e) = rx.await {
+    if let Err(e) = send_loop_sender.send((message, tx)) {
+        // the device was never asked, once it has been it may power off without acking
+        disconnect_expected.store(false, Ordering::SeqCst);
         return Err(e.into());
     }
-    Ok(())
+    rx.await?
 }
 
-/// reads messages from `stream`, deserializes them, and sends them to `queue`
-async fn recv_loop<T>(stream: ReadHalf<T>, recv_loop_sender: mpsc::UnboundedSender<Result<Message>>)
-where
+async fn send_unchecked(
+    send_loop_sender: &mpsc::UnboundedSender<MessageReturnError>,
+    message: Message,
+) -> Result<()> {
+    let (tx, rx) = oneshot::channel();
+    let full = (message, tx);
+    send_loop_sender.send(full)?;
+    rx.await?
+}
+
+/// reads messages from `stream`, deserializes them, and sends them to `queue`, until `stream` is
+/// closed
+async fn recv_loop<T>(
+    stream: ReadHalf<T>,
+    recv_loop_sender: mpsc::UnboundedSender<Result<Message>>,
+    ack_sender: mpsc::UnboundedSender<AckEvent>,
+    disconnect_expected: Arc<AtomicBool>,
+    alert_dispatcher: AlertDispatcher,
+    protocol_version: Arc<AtomicU32>,
+    traffic: Arc<Mutex<Traffic>>,
+) where
     T: AsyncRead,
 {
     let mut stream = BufReader::new(stream);
     loop {
-        if let Err(_) = recv_loop_sender.send(recv_loop_inner(&mut stream).await) {
+        let version = ProtocolVersion(protocol_version.load(Ordering::SeqCst));
+        let res = recv_loop_inner(&mut stream, version).await;
+
+        if let Ok(message) = &res {
+            traffic.lock().unwrap().record_received(message);
+            // the send loop only goes away with the queue, at which point acks don't matter
+            if let Data::Ack(_) = message.data {
+                let _ = ack_sender.send(AckEvent::Received(message.sequence_number));
+            } else if message.requires_ack() {
+                let _ = ack_sender.send(AckEvent::Send(message.sequence_number));
+            }
+        }
+
+        if let Ok(Message {
+            data:
+                Data::DataMdr(DataMdr {
+                    command: Command::ConnectRetProtocolInfo(info),
+                }),
+            ..
+        }) = &res
+        {
+            protocol_version.store(info.protocol_version().0, Ordering::SeqCst);
+        }
+
+        if let Ok(Message {
+            data:
+                Data::DataMdr(DataMdr {
+                    command: Command::AlertNtfyParam(alert),
+                }),
+            ..
+        }) = &res
+        {
+            alert_dispatcher.dispatch(*alert);
+        }
+
+        if let Err(e) = &res {
+            if is_disconnect(e) {
+                // an expected disconnect is reported by the channel closing
+                if !disconnect_expected.load(Ordering::SeqCst) {
+                    let _ = recv_loop_sender.send(res);
+                }
+                return;
+            }
+        }
+
+        if recv_loop_sender.send(res).is_err() {
             // nothing can be done here
             return;
         }
     }
 }
 
-async fn recv_loop_inner<T>(stream: &mut BufReader<T>) -> Result<Message>
+fn is_disconnect(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<io::Error>() {
+        Some(e) => matches!(
+            e.kind(),
+            io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+        ),
+        None => false,
+    }
+}
+
+async fn recv_loop_inner<T>(stream: &mut BufReader<T>, version: ProtocolVersion) -> Result<Message>
 where
     T: AsyncRead + Unpin,
 {
@@ -104,7 +450,7 @@ where
     let mut buf = vec![message::MESSAGE_START];
     stream.read_until(message::MESSAGE_END, &mut buf).await?;
 
-    let message = Message::deserialize(&buf)?;
+    let message = Message::deserialize_for(&buf, version)?;
     println!("recv: {:?}", message);
 
     Ok(message)
@@ -123,59 +469,515 @@ where
     Ok(())
 }
 
-/// receives messages from `queue`, serializes them, and writes them to `stream`
+/// receives messages from `queue`, serializes them, and writes them to `stream`. Messages that
+/// require an ack are sent one at a time, each waiting for its ack; shots and acks are written
+/// straight away. Answers to alerts are sent the same way, but only for as long as the queue
+/// itself is around.
 async fn send_loop<T>(
     mut stream: WriteHalf<T>,
     mut send_loop_receiver: mpsc::UnboundedReceiver<MessageReturnError>,
+    mut alert_response_receiver: mpsc::UnboundedReceiver<MessageReturnError>,
+    mut ack_receiver: mpsc::UnboundedReceiver<AckEvent>,
+    protocol_version: Arc<AtomicU32>,
+    traffic: Arc<Mutex<Traffic>>,
 ) where
     T: AsyncWrite,
 {
+    let mut sequence_number = 0;
+    let mut acks_open = true;
+    let mut alert_responses_open = true;
     loop {
-        let (message, tx) = match send_loop_receiver.recv().await {
-            Some(x) => x,
-            None => break,
+        let (message, tx) = tokio::select! {
+            x = send_loop_receiver.recv() => match x {
+                Some(x) => x,
+                None => break,
+            },
+            x = alert_response_receiver.recv(), if alert_responses_open => match x {
+                Some(x) => x,
+                None => {
+                    alert_responses_open = false;
+                    continue;
+                }
+            },
+            x = ack_receiver.recv(), if acks_open => match x {
+                Some(AckEvent::Send(n)) => {
+                    if send_ack(&mut stream, n).await.is_err() {
+                        // the recv loop reports the stream closing
+                        return;
+                    }
+                    continue;
+                }
+                // an ack arriving after its message was given up on
+                Some(AckEvent::Received(_)) => continue,
+                None => {
+                    acks_open = false;
+                    continue;
+                }
+            },
         };
-        if let Err(_) = tx.send(send_loop_inner(&mut stream, message).await) {
-            // nothing can be done here
-            return;
+
+        let version = ProtocolVersion(protocol_version.load(Ordering::SeqCst));
+        traffic.lock().unwrap().record_sent(&message);
+        let res = if message.requires_ack() {
+            send_acknowledged(
+                &mut stream,
+                &mut ack_receiver,
+                &mut sequence_number,
+                message,
+                version,
+            )
+            .await
+        } else {
+            send_loop_inner(&mut stream, message, version).await
+        };
+        // the sender may have stopped waiting, which is fine
+        let _ = tx.send(res);
+    }
+}
+
+/// writes `message` with the current sequence number, replacing its own, and waits for the
+/// device to acknowledge it, sending it again if it doesn't. Acks owed to the device are sent in
+/// the meantime.
+async fn send_acknowledged<T>(
+    stream: &mut WriteHalf<T>,
+    ack_receiver: &mut mpsc::UnboundedReceiver<AckEvent>,
+    sequence_number: &mut u8,
+    mut message: Message,
+    version: ProtocolVersion,
+) -> Result<()>
+where
+    T: AsyncWrite,
+{
+    message.sequence_number = *sequence_number;
+    println!("send: {:?}", message);
+    let bytes = message.serialize_for(version);
+
+    for _ in 0..=ACK_RETRIES {
+        stream.write_all(&bytes).await?;
+
+        let deadline = Instant::now() + ACK_TIMEOUT;
+        loop {
+            match timeout_at(deadline, ack_receiver.recv()).await {
+                // the device acks with the sequence number it expects next
+                Ok(Some(AckEvent::Received(n))) if n != *sequence_number => {
+                    *sequence_number = n;
+                    return Ok(());
+                }
+                // a duplicate ack for an earlier message
+                Ok(Some(AckEvent::Received(_))) => {}
+                Ok(Some(AckEvent::Send(n))) => send_ack(stream, n).await?,
+                // the recv loop has stopped, so no ack can arrive
+                Ok(None) => return Err(MessageQueueError::NoAck.into()),
+                Err(_) => break,
+            }
         }
     }
+    Err(MessageQueueError::NoAck.into())
 }
 
-async fn send_loop_inner<T>(stream: &mut WriteHalf<T>, message: Message) -> Result<()>
+async fn send_ack<T>(stream: &mut WriteHalf<T>, received_sequence_number: u8) -> Result<()>
+where
+    T: AsyncWrite,
+{
+    let ack = Message {
+        sequence_number: received_sequence_number ^ 1,
+        data: Data::Ack(Ack {}),
+    };
+    stream
+        .write_all(&ack.serialize())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn send_loop_inner<T>(
+    stream: &mut WriteHalf<T>,
+    message: Message,
+    version: ProtocolVersion,
+) -> Result<()>
 where
     T: AsyncWrite,
 {
     println!("send: {:?}", message);
     stream
-        .write_all(&message.serialize())
+        .write_all(&message.serialize_for(version))
         .await
         .map_err(|e| e.into())
 }
 
+#[derive(Debug)]
 pub struct RecvHalf {
     recv_loop_receiver: mpsc::UnboundedReceiver<Result<Message>>,
+    set_aside: VecDeque<Result<Message>>,
 }
 
 impl RecvHalf {
     pub async fn recv(&mut self) -> Option<Result<Message>> {
-        recv_priv(&mut self.recv_loop_receiver).await
+        recv_priv(&mut self.recv_loop_receiver, &mut self.set_aside).await
+    }
+
+    /// See `MessageQueue::recv_matching`
+    pub async fn recv_matching<T, F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnMut(&Message) -> Option<T>,
+    {
+        recv_matching_priv(&mut self.recv_loop_receiver, &mut self.set_aside, f).await
     }
 
     pub fn unsplit(self, send_half: SendHalf) -> MessageQueue {
         MessageQueue {
             recv_loop_receiver: self.recv_loop_receiver,
+            set_aside: self.set_aside,
             send_loop_sender: send_half.send_loop_sender,
+            disconnect_expected: send_half.disconnect_expected,
+            alert_callback: send_half.alert_callback,
+            protocol_version: send_half.protocol_version,
+            traffic: send_half.traffic,
         }
     }
 }
 
+#[derive(Debug)]
 pub struct SendHalf {
     send_loop_sender: mpsc::UnboundedSender<MessageReturnError>,
+    disconnect_expected: Arc<AtomicBool>,
+    alert_callback: AlertCallbackSlot,
+    protocol_version: Arc<AtomicU32>,
+    traffic: Arc<Mutex<Traffic>>,
 }
 
 impl SendHalf {
-    pub async fn send(&mut self, message: Message) -> Result<()> {
+    /// See `MessageQueue::send`
+    pub async fn send(&self, message: Message) -> Result<()> {
+        send_priv(&self.send_loop_sender, message).await
+    }
+
+    pub async fn power_off(&self) -> Result<()> {
+        power_off_priv(&self.send_loop_sender, &self.disconnect_expected).await
+    }
+
+    pub fn set_alert_callback<F>(&self, callback: F)
+    where
+        F: Fn(AlertNtfyParam, AlertResponder) + Send + Sync + 'static,
+    {
+        self.alert_callback.set(Arc::new(callback));
+    }
+}
+
+/// Sends on a queue without borrowing it. Like `MessageQueue::send`, it refuses to power the
+/// device off.
+#[derive(Clone, Debug)]
+pub struct MessageSender {
+    send_loop_sender: mpsc::UnboundedSender<MessageReturnError>,
+}
+
+impl MessageSender {
+    pub async fn send(&self, message: Message) -> Result<()> {
         send_priv(&self.send_loop_sender, message).await
     }
 }
+
+/// Stops a running operation from outside, e.g. while its progress is being awaited. Cancelling
+/// only asks the device to stop, the operation then finishes as it normally would.
+#[derive(Clone, Debug)]
+pub struct Canceller {
+    sender: MessageSender,
+    cancel_message: fn() -> Message,
+}
+
+impl Canceller {
+    pub fn new(sender: MessageSender, cancel_message: fn() -> Message) -> Self {
+        Self {
+            sender,
+            cancel_message,
+        }
+    }
+
+    pub async fn cancel(&self) -> Result<()> {
+        self.sender.send((self.cancel_message)()).await
+    }
+}
+
+/// Answers a single alert. It can be held on to and answered later, e.g. after asking the user.
+/// Answering fails once the queue is gone.
+#[derive(Debug)]
+pub struct AlertResponder {
+    alert: AlertNtfyParam,
+    alert_response_sender: mpsc::UnboundedSender<MessageReturnError>,
+}
+
+impl AlertResponder {
+    pub fn alert(&self) -> AlertNtfyParam {
+        self.alert
+    }
+
+    /// Answers the alert with `action`. Alerts with `AlertActionType::ConfirmationOnly` can't be
+    /// answered with `AlertAction::Negative`.
+    pub async fn respond(self, action: AlertAction) -> Result<()> {
+        let AlertNtfyParam(inquired_type, message_type, action_type) = self.alert;
+        if action_type == AlertActionType::ConfirmationOnly && action == AlertAction::Negative {
+            return Err(MessageQueueError::AlertNotRejectable(self.alert).into());
+        }
+
+        let message = Message {
+            sequence_number: 0,
+            data: Data::DataMdr(DataMdr {
+                command: Command::AlertSetParam(AlertSetParam(inquired_type, message_type, action)),
+            }),
+        };
+        send_unchecked(&self.alert_response_sender, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::alert::{AlertInquiredType, AlertMessageType};
+    use crate::message::data_mdr::connect::ConnectRetProtocolInfo;
+    use crate::test_device;
+
+    fn alert() -> AlertNtfyParam {
+        AlertNtfyParam(
+            AlertInquiredType::FixedMessage,
+            AlertMessageType::DisconnectCausedByChangeToLdac,
+            AlertActionType::PositiveNegative,
+        )
+    }
+
+    fn alert_message() -> Message {
+        Message::data_mdr(Command::AlertNtfyParam(alert()))
+    }
+
+    /// Waits for the callback to hand over the responder
+    async fn responder(receiver: &mut mpsc::UnboundedReceiver<AlertResponder>) -> AlertResponder {
+        tokio::time::timeout(Duration::from_secs(1), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    fn data_message() -> Message {
+        Message::data_mdr(Command::ConnectGetProtocolInfo(ConnectGetProtocolInfo(
+            ConnectInquiredType::Fixed,
+        )))
+    }
+
+    fn is_no_ack(e: &anyhow::Error) -> bool {
+        matches!(
+            e.downcast_ref::<MessageQueueError>(),
+            Some(MessageQueueError::NoAck)
+        )
+    }
+
+    #[tokio::test]
+    async fn retries_until_acknowledged() {
+        let (message_queue, mut device) = test_device::connect_raw();
+        let sender = message_queue.sender();
+        let send = tokio::spawn(async move { sender.send(data_message()).await });
+
+        let first = device.read().await;
+        let second = device.read().await;
+        assert_eq!(second.serialize(), first.serialize());
+        device.ack(second.sequence_number).await;
+        send.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_without_ack() {
+        let (message_queue, mut device) = test_device::connect_raw();
+        let sender = message_queue.sender();
+        let send = tokio::spawn(async move { sender.send(data_message()).await });
+
+        for _ in 0..=ACK_RETRIES {
+            device.read().await;
+        }
+        assert!(is_no_ack(&send.await.unwrap().unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn ignores_duplicate_acks() {
+        let (message_queue, mut device) = test_device::connect_raw();
+        let sender = message_queue.sender();
+        let send = tokio::spawn(async move {
+            sender.send(data_message()).await?;
+            sender.send(data_message()).await
+        });
+
+        let first = device.read().await;
+        device.ack(first.sequence_number).await;
+        let second = device.read().await;
+        assert_ne!(second.sequence_number, first.sequence_number);
+        // acknowledging the first message again doesn't acknowledge the second, so it is sent
+        // again
+        device.ack(first.sequence_number).await;
+        let repeated = device.read().await;
+        assert_eq!(repeated.serialize(), second.serialize());
+        device.ack(second.sequence_number).await;
+        send.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn acks_the_device_while_waiting_for_an_ack() {
+        let (mut message_queue, mut device) = test_device::connect_raw();
+        let sender = message_queue.sender();
+        let send = tokio::spawn(async move { sender.send(data_message()).await });
+
+        let sent = device.read().await;
+        let mut received = data_message();
+        received.sequence_number = 1;
+        device.write(&received).await;
+        let ack = device.read().await;
+        assert!(matches!(ack.data, Data::Ack(_)));
+        assert_eq!(ack.sequence_number, 0);
+
+        device.ack(sent.sequence_number).await;
+        send.await.unwrap().unwrap();
+        assert!(matches!(
+            message_queue.recv().await,
+            Some(Ok(Message {
+                data: Data::DataMdr(DataMdr {
+                    command: Command::ConnectGetProtocolInfo(_)
+                }),
+                ..
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn acks_any_sequence_number() {
+        let (_message_queue, mut device) = test_device::connect_raw();
+        let mut received = data_message();
+        received.sequence_number = 2;
+        device.write(&received).await;
+        let ack = device.read().await;
+        assert!(matches!(ack.data, Data::Ack(_)));
+        assert_eq!(ack.sequence_number, 3);
+    }
+
+    #[tokio::test]
+    async fn negotiates_the_protocol_version() {
+        let (mut message_queue, _device) = test_device::connect(|command| match command {
+            Command::ConnectGetProtocolInfo(_) => {
+                vec![Message::data_mdr(Command::ConnectRetProtocolInfo(
+                    ConnectRetProtocolInfo(ConnectInquiredType::Fixed, 0x0200),
+                ))]
+            }
+            _ => vec![],
+        });
+        assert_eq!(message_queue.protocol_version(), ProtocolVersion::V1);
+        assert_eq!(
+            message_queue.negotiate_protocol_version().await.unwrap(),
+            ProtocolVersion::V2
+        );
+        assert_eq!(message_queue.protocol_version(), ProtocolVersion::V2);
+    }
+
+    #[tokio::test]
+    async fn protocol_version_times_out() {
+        let (mut message_queue, _device) = test_device::connect(|_| vec![]);
+        assert!(message_queue.negotiate_protocol_version().await.is_err());
+        assert_eq!(message_queue.protocol_version(), ProtocolVersion::V1);
+    }
+
+    #[tokio::test]
+    async fn answers_alerts() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        message_queue.set_alert_callback(move |_, responder| {
+            let _ = tx.send(responder);
+        });
+
+        device.send(alert_message());
+        let responder = responder(&mut rx).await;
+        assert_eq!(responder.alert(), alert());
+        responder.respond(AlertAction::Positive).await.unwrap();
+
+        assert!(matches!(
+            device.received().last(),
+            Some(Command::AlertSetParam(AlertSetParam(
+                _,
+                AlertMessageType::DisconnectCausedByChangeToLdac,
+                AlertAction::Positive
+            )))
+        ));
+    }
+
+    #[tokio::test]
+    async fn callback_can_replace_itself() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let message_queue = Arc::new(message_queue);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = message_queue.clone();
+        message_queue.set_alert_callback(move |_, responder| {
+            let tx = tx.clone();
+            queue.set_alert_callback(move |_, responder| {
+                let _ = tx.send(responder);
+            });
+            drop(responder);
+        });
+
+        device.send(alert_message());
+        device.send(alert_message());
+        responder(&mut rx).await;
+    }
+
+    #[tokio::test]
+    async fn responders_fail_once_the_queue_is_gone() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        message_queue.set_alert_callback(move |_, responder| {
+            let _ = tx.send(responder);
+        });
+
+        device.send(alert_message());
+        let responder = responder(&mut rx).await;
+        drop(message_queue);
+        // time is paused, so this returns once the send loop has nothing left to do
+        tokio::time::sleep(ACK_TIMEOUT).await;
+
+        assert!(responder.respond(AlertAction::Positive).await.is_err());
+        assert!(device.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refuses_unconfirmed_power_offs() {
+        let (message_queue, device) = test_device::connect(|_| vec![]);
+        let command = || {
+            Command::CommonSetPowerOff(CommonSetPowerOff(
+                PowerOffInquiredType::Fixed,
+                PowerOffSettingValue::PowerOff,
+            ))
+        };
+        for data in vec![
+            Data::DataMdr(DataMdr { command: command() }),
+            Data::ShotMdr(DataMdr { command: command() }),
+        ] {
+            let message = Message {
+                sequence_number: 0,
+                data,
+            };
+            assert!(matches!(
+                message_queue
+                    .send(message)
+                    .await
+                    .unwrap_err()
+                    .downcast_ref(),
+                Some(MessageQueueError::UnconfirmedPowerOff)
+            ));
+        }
+        assert!(device.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expects_the_disconnect_after_an_unacknowledged_power_off() {
+        let (mut message_queue, mut device) = test_device::connect_raw();
+        let (res, _) = tokio::join!(message_queue.power_off(), async {
+            for _ in 0..=ACK_RETRIES {
+                device.read().await;
+            }
+        });
+        assert!(is_no_ack(&res.unwrap_err()));
+
+        drop(device);
+        assert!(message_queue.recv().await.is_none());
+    }
+}
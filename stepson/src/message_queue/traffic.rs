@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::message::Message;
+
+/// How far back rates are averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Messages per second in each direction, averaged over the last few seconds. Acks aren't
+/// counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrafficRates {
+    pub data_sent: f64,
+    pub data_received: f64,
+    pub shots_sent: f64,
+    pub shots_received: f64,
+}
+
+/// Counts acknowledged data and shots separately, since shots tend to arrive in bursts of
+/// notifications that would otherwise hide how busy the acknowledged traffic is
+#[derive(Debug, Default)]
+pub(super) struct Traffic {
+    data_sent: RateMeter,
+    data_received: RateMeter,
+    shots_sent: RateMeter,
+    shots_received: RateMeter,
+}
+
+impl Traffic {
+    pub fn record_sent(&mut self, message: &Message) {
+        if message.is_shot() {
+            self.shots_sent.record();
+        } else if message.requires_ack() {
+            self.data_sent.record();
+        }
+    }
+
+    pub fn record_received(&mut self, message: &Message) {
+        if message.is_shot() {
+            self.shots_received.record();
+        } else if message.requires_ack() {
+            self.data_received.record();
+        }
+    }
+
+    pub fn rates(&mut self) -> TrafficRates {
+        TrafficRates {
+            data_sent: self.data_sent.rate(),
+            data_received: self.data_received.rate(),
+            shots_sent: self.shots_sent.rate(),
+            shots_received: self.shots_received.rate(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RateMeter {
+    events: VecDeque<Instant>,
+}
+
+impl RateMeter {
+    fn record(&mut self) {
+        let now = Instant::now();
+        self.expire(now);
+        self.events.push_back(now);
+    }
+
+    fn rate(&mut self) -> f64 {
+        self.expire(Instant::now());
+        self.events.len() as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(&t) = self.events.front() {
+            if now.duration_since(t) < RATE_WINDOW {
+                break;
+            }
+            self.events.pop_front();
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::message::data_mdr::opt::{
+    NcOptimizerControl, NcOptimizerStatus, OptInquiredType, OptNtfyParam, OptNtfyStatus,
+    OptSetStatus,
+};
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message};
+use crate::message_queue::{Canceller, MessageQueue};
+
+/// How long the headset may go without reporting progress
+const NC_OPTIMIZER_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, Error)]
+pub enum NcOptimizerError {
+    #[error("the optimizer stopped before finishing")]
+    Stopped,
+    #[error("the headset stopped reporting progress")]
+    Timeout,
+    #[error("the connection closed before the optimizer finished")]
+    Disconnected,
+}
+
+/// The result of a successful optimization
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NcOptimizerResult {
+    /// The measured atmospheric pressure, in tenths of an atmosphere, if the headset reported it
+    pub atmospheric_pressure: Option<u8>,
+}
+
+#[derive(Debug)]
+pub enum NcOptimizerProgress {
+    Status(NcOptimizerStatus),
+    /// The measured atmospheric pressure, in tenths of an atmosphere
+    AtmosphericPressure(u8),
+    /// Always the last progress update
+    Done(Result<NcOptimizerResult, NcOptimizerError>),
+}
+
+/// A running NC optimizer. Progress is read from the notifications the headset sends, anything
+/// else received meanwhile is set aside as described for `MessageQueue::recv_matching`.
+pub struct NcOptimizer<'a> {
+    message_queue: &'a mut MessageQueue,
+    status: NcOptimizerStatus,
+    atmospheric_pressure: Option<u8>,
+    result: Option<Result<NcOptimizerResult, NcOptimizerError>>,
+}
+
+/// A notification about the optimizer
+enum Update {
+    Status(NcOptimizerStatus),
+    AtmosphericPressure(u8),
+}
+
+impl<'a> NcOptimizer<'a> {
+    pub async fn start(message_queue: &'a mut MessageQueue) -> Result<NcOptimizer<'a>> {
+        message_queue
+            .send(set_status_message(NcOptimizerControl::Start))
+            .await?;
+
+        Ok(Self {
+            message_queue,
+            status: NcOptimizerStatus::NotRunning,
+            atmospheric_pressure: None,
+            result: None,
+        })
+    }
+
+    /// Asks the headset to stop optimizing. The optimizer then finishes with
+    /// `NcOptimizerError::Stopped`.
+    pub async fn cancel(&mut self) -> Result<()> {
+        self.canceller().cancel().await
+    }
+
+    /// For cancelling while `next` or `wait` is being awaited
+    pub fn canceller(&self) -> Canceller {
+        Canceller::new(self.message_queue.sender(), || {
+            set_status_message(NcOptimizerControl::Cancel)
+        })
+    }
+
+    /// Waits for the next progress update, returning `None` after `NcOptimizerProgress::Done`
+    pub async fn next(&mut self) -> Option<NcOptimizerProgress> {
+        if self.result.is_some() {
+            return None;
+        }
+
+        let update = self
+            .message_queue
+            .recv_matching(|message| match &message.data {
+                Data::DataMdr(DataMdr {
+                    command:
+                        Command::OptNtfyStatus(OptNtfyStatus(OptInquiredType::NcOptimizer, status)),
+                }) => Some(Update::Status(*status)),
+                Data::DataMdr(DataMdr {
+                    command:
+                        Command::OptNtfyParam(OptNtfyParam(OptInquiredType::NcOptimizer, pressure)),
+                }) => Some(Update::AtmosphericPressure(*pressure)),
+                _ => None,
+            });
+        let update = match tokio::time::timeout(NC_OPTIMIZER_TIMEOUT, update).await {
+            Ok(update) => update,
+            Err(_) => return Some(self.finish(Err(NcOptimizerError::Timeout))),
+        };
+
+        Some(match update {
+            Some(Update::Status(status)) => {
+                let previous = std::mem::replace(&mut self.status, status);
+                match status {
+                    NcOptimizerStatus::Finished => self.finish(Ok(NcOptimizerResult {
+                        atmospheric_pressure: self.atmospheric_pressure,
+                    })),
+                    // the headset reports `NotRunning` before starting as well, which only means
+                    // failure once it has actually been running
+                    NcOptimizerStatus::NotRunning if previous != NcOptimizerStatus::NotRunning => {
+                        self.finish(Err(NcOptimizerError::Stopped))
+                    }
+                    _ => NcOptimizerProgress::Status(status),
+                }
+            }
+            Some(Update::AtmosphericPressure(pressure)) => {
+                self.atmospheric_pressure = Some(pressure);
+                NcOptimizerProgress::AtmosphericPressure(pressure)
+            }
+            None => self.finish(Err(NcOptimizerError::Disconnected)),
+        })
+    }
+
+    /// Waits for the optimizer to finish, ignoring intermediate progress. If `next` has already
+    /// returned `NcOptimizerProgress::Done`, this returns the same result.
+    pub async fn wait(mut self) -> Result<NcOptimizerResult, NcOptimizerError> {
+        while self.next().await.is_some() {}
+        self.result
+            .expect("`next` only returns `None` once there is a result")
+    }
+
+    fn finish(&mut self, res: Result<NcOptimizerResult, NcOptimizerError>) -> NcOptimizerProgress {
+        self.result = Some(res);
+        NcOptimizerProgress::Done(res)
+    }
+}
+
+fn set_status_message(control: NcOptimizerControl) -> Message {
+    Message::data_mdr(Command::OptSetStatus(OptSetStatus(
+        OptInquiredType::NcOptimizer,
+        control,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::data_mdr::common::{
+        BatteryChargingStatus, BatteryLevel, BatteryStatus, CommonNtfyBatteryLevel,
+    };
+    use crate::message::{MESSAGE_END, MESSAGE_START};
+    use crate::test_device::{self, TestDevice};
+
+    fn status(status: NcOptimizerStatus) -> Message {
+        Message::data_mdr(Command::OptNtfyStatus(OptNtfyStatus(
+            OptInquiredType::NcOptimizer,
+            status,
+        )))
+    }
+
+    fn pressure(pressure: u8) -> Message {
+        Message::data_mdr(Command::OptNtfyParam(OptNtfyParam(
+            OptInquiredType::NcOptimizer,
+            pressure,
+        )))
+    }
+
+    fn battery() -> Message {
+        Message::data_mdr(Command::CommonNtfyBatteryLevel(CommonNtfyBatteryLevel(
+            BatteryStatus::Battery(BatteryLevel(40, BatteryChargingStatus::NotCharging)),
+        )))
+    }
+
+    /// A headset that sends `steps` right after connecting, and reports `NotRunning` when the
+    /// optimizer is cancelled
+    fn connect(steps: fn(&TestDevice)) -> (MessageQueue, TestDevice) {
+        let (message_queue, device) = test_device::connect(|command| match command {
+            Command::OptSetStatus(OptSetStatus(_, NcOptimizerControl::Cancel)) => {
+                vec![status(NcOptimizerStatus::NotRunning)]
+            }
+            _ => vec![],
+        });
+        steps(&device);
+        (message_queue, device)
+    }
+
+    async fn progress(optimizer: &mut NcOptimizer<'_>) -> Vec<String> {
+        let mut progress = vec![];
+        while let Some(p) = optimizer.next().await {
+            progress.push(format!("{:?}", p));
+        }
+        progress
+    }
+
+    #[tokio::test]
+    async fn reports_progress_until_finished() {
+        let (mut message_queue, _device) = connect(|device| {
+            device.send(status(NcOptimizerStatus::NotRunning));
+            device.send(status(NcOptimizerStatus::WearingCondition));
+            device.send(status(NcOptimizerStatus::AtmosphericPressure));
+            device.send(pressure(9));
+            device.send(status(NcOptimizerStatus::Analyzing));
+            device.send(status(NcOptimizerStatus::Finished));
+        });
+        let mut optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+
+        assert_eq!(
+            progress(&mut optimizer).await,
+            vec![
+                "Status(NotRunning)",
+                "Status(WearingCondition)",
+                "Status(AtmosphericPressure)",
+                "AtmosphericPressure(9)",
+                "Status(Analyzing)",
+                "Done(Ok(NcOptimizerResult { atmospheric_pressure: Some(9) }))",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_while_waiting() {
+        let (mut message_queue, device) = connect(|device| {
+            device.send(status(NcOptimizerStatus::WearingCondition));
+        });
+        let mut optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+        assert!(matches!(
+            optimizer.next().await,
+            Some(NcOptimizerProgress::Status(
+                NcOptimizerStatus::WearingCondition
+            ))
+        ));
+
+        let canceller = optimizer.canceller();
+        let (res, cancelled) = tokio::join!(optimizer.wait(), canceller.cancel());
+        cancelled.unwrap();
+        assert!(matches!(res, Err(NcOptimizerError::Stopped)));
+        assert!(matches!(
+            device.received().last(),
+            Some(Command::OptSetStatus(OptSetStatus(
+                _,
+                NcOptimizerControl::Cancel
+            )))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sets_aside_unrelated_traffic() {
+        let (mut message_queue, _device) = connect(|device| {
+            device.send(status(NcOptimizerStatus::WearingCondition));
+            device.send(battery());
+            // a frame that can't be decoded
+            device.send_raw(vec![MESSAGE_START, MESSAGE_END]);
+            device.send(status(NcOptimizerStatus::Finished));
+        });
+        let optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+        assert_eq!(
+            optimizer.wait().await.unwrap(),
+            NcOptimizerResult {
+                atmospheric_pressure: None
+            }
+        );
+
+        assert!(matches!(
+            message_queue.recv().await,
+            Some(Ok(Message {
+                data: Data::DataMdr(DataMdr {
+                    command: Command::CommonNtfyBatteryLevel(_)
+                }),
+                ..
+            }))
+        ));
+        assert!(matches!(message_queue.recv().await, Some(Err(_))));
+    }
+
+    #[tokio::test]
+    async fn disconnected() {
+        let (mut message_queue, device) = connect(|device| {
+            device.send(status(NcOptimizerStatus::WearingCondition));
+        });
+        let optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+        device.disconnect();
+        assert!(matches!(
+            optimizer.wait().await,
+            Err(NcOptimizerError::Disconnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_keeps_the_result_of_next() {
+        let (mut message_queue, _device) = connect(|device| {
+            device.send(pressure(9));
+            device.send(status(NcOptimizerStatus::Finished));
+        });
+        let mut optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+        progress(&mut optimizer).await;
+        assert_eq!(
+            optimizer.wait().await.unwrap(),
+            NcOptimizerResult {
+                atmospheric_pressure: Some(9)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out_without_progress() {
+        let (mut message_queue, _device) = connect(|device| {
+            device.send(status(NcOptimizerStatus::WearingCondition));
+        });
+        let mut optimizer = NcOptimizer::start(&mut message_queue).await.unwrap();
+        assert!(matches!(
+            optimizer.next().await,
+            Some(NcOptimizerProgress::Status(
+                NcOptimizerStatus::WearingCondition
+            ))
+        ));
+        assert!(matches!(
+            optimizer.next().await,
+            Some(NcOptimizerProgress::Done(Err(NcOptimizerError::Timeout)))
+        ));
+        assert!(optimizer.next().await.is_none());
+    }
+}
@@ -5,7 +5,10 @@ pub use completion::*;
 pub use from_repl::*;
 
 use std::cell::RefCell;
+use std::io;
+use std::io::Write;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
 use rustyline::config::{CompletionType, Config};
@@ -13,8 +16,24 @@ use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
 use crate::bluetooth::{AsyncBtStream, Device, Manager};
+use crate::fit_test::FitTest;
+use crate::general_settings;
+use crate::message::data_mdr::alert::{
+    AlertAction, AlertActionType, AlertInquiredType, AlertNtfyParam, AlertSetStatus,
+    AlertStatusValue,
+};
+use crate::message::data_mdr::eq_ebb::{EqEbbInquiredType, EqEbbSetParam, EqSetting};
+use crate::message::data_mdr::fit::EarpieceFit;
+use crate::message::data_mdr::general_setting::{
+    GsCapability, GsInquiredType, GsSetParam, GsString,
+};
+use crate::message::data_mdr::play::{
+    MusicVolume, PlayInquiredType, PlayParam, PlaySetParam, PlaySetStatus, PlaybackControl,
+    MUSIC_VOLUME_MAX,
+};
+use crate::message::data_mdr::Command;
 use crate::message::Message;
-use crate::message_queue::MessageQueue;
+use crate::message_queue::{AlertResponder, MessageQueue};
 
 type ShouldExit = bool;
 
@@ -22,6 +41,10 @@ struct ReplData {
     manager: Rc<Manager>,
     device: Option<Device>,
     message_queue: Option<MessageQueue>,
+    /// the last alert sent by the device, until it is answered with the `alert` command
+    pending_alert: Arc<Mutex<Option<AlertResponder>>>,
+    /// the general settings found by the last `gs` without arguments
+    general_settings: Vec<(GsInquiredType, GsCapability)>,
 }
 
 pub struct Repl {
@@ -31,12 +54,47 @@ pub struct Repl {
 impl ReplCompletionStateful for ReplData {
     fn lazy_completion_tree(&self) -> Box<dyn FnOnce() -> CompletionTree> {
         let manager = self.manager.clone();
-        Box::new(move || CompletionTree::new(vec![
-            ("connect".to_string(), manager.lazy_completion_tree()),
-            ("devices".to_string(), CompletionTree::lazy_empty()),
-            ("sendll".to_string(), Message::lazy_completion_tree()),
-            ("quit".to_string(), CompletionTree::lazy_empty()),
-        ]))
+        let general_settings = self.general_settings.clone();
+        Box::new(move || {
+            CompletionTree::new(vec![
+                ("connect".to_string(), manager.lazy_completion_tree()),
+                ("devices".to_string(), CompletionTree::lazy_empty()),
+                (
+                    "alert".to_string(),
+                    Box::new(|| {
+                        CompletionTree::new(vec![
+                            ("accept".to_string(), CompletionTree::lazy_empty()),
+                            ("reject".to_string(), CompletionTree::lazy_empty()),
+                            ("enable".to_string(), CompletionTree::lazy_empty()),
+                            ("disable".to_string(), CompletionTree::lazy_empty()),
+                        ])
+                    }),
+                ),
+                ("sendll".to_string(), Message::lazy_completion_tree()),
+                ("eq".to_string(), EqSetting::lazy_completion_tree()),
+                ("fittest".to_string(), CompletionTree::lazy_empty()),
+                (
+                    "gs".to_string(),
+                    Box::new(move || general_settings_completion_tree(general_settings)),
+                ),
+                (
+                    "playback".to_string(),
+                    PlaybackControl::lazy_completion_tree(),
+                ),
+                ("volume".to_string(), CompletionTree::lazy_empty()),
+                ("traffic".to_string(), CompletionTree::lazy_empty()),
+                (
+                    "poweroff".to_string(),
+                    Box::new(|| {
+                        CompletionTree::new(vec![(
+                            "force".to_string(),
+                            CompletionTree::lazy_empty(),
+                        )])
+                    }),
+                ),
+                ("quit".to_string(), CompletionTree::lazy_empty()),
+            ])
+        })
     }
 }
 
@@ -46,6 +104,8 @@ impl Repl {
             manager: Rc::new(Manager::new()?),
             device: None,
             message_queue: None,
+            pending_alert: Arc::new(Mutex::new(None)),
+            general_settings: vec![],
         }));
         Ok(Self { data })
     }
@@ -92,7 +152,15 @@ impl Repl {
             None => Ok(false),
             Some("connect") => self.connect(&mut words).await,
             Some("devices") => self.devices(&mut words).await,
+            Some("alert") => self.alert(&mut words).await,
             Some("sendll") => self.send(&mut words).await,
+            Some("eq") => self.eq(&mut words).await,
+            Some("fittest") => self.fit_test(&mut words).await,
+            Some("gs") => self.general_setting(&mut words).await,
+            Some("playback") => self.playback(&mut words).await,
+            Some("volume") => self.volume(&mut words).await,
+            Some("traffic") => self.traffic(&mut words),
+            Some("poweroff") => self.power_off(&mut words).await,
             Some("quit") => self.quit(&mut words).await,
             Some(w) => self.unknown_command(w),
         };
@@ -122,7 +190,7 @@ impl Repl {
             }
         };
 
-        if let Some(_) = words.next() {
+        if words.next().is_some() {
             println!("connect: too many arguments, expected 1");
             return Ok(false);
         }
@@ -137,12 +205,30 @@ impl Repl {
         };
 
         let bt_stream = AsyncBtStream::new(device.bt_stream()?)?;
-        let message_queue = MessageQueue::new(bt_stream);
+        let mut message_queue = MessageQueue::new(bt_stream);
+        // everything else is serialized for the version, so it has to be known first
+        match message_queue.negotiate_protocol_version().await {
+            Ok(version) => println!("connect: protocol version {:#06x}", version.0),
+            Err(e) => println!(
+                "connect: {}, assuming protocol version {:#06x}",
+                e,
+                message_queue.protocol_version().0
+            ),
+        }
+
+        let pending_alert = self.data.borrow().pending_alert.clone();
+        // an alert of the previous device can't be answered anymore
+        *pending_alert.lock().unwrap() = None;
+        message_queue.set_alert_callback(move |alert, responder| {
+            print_alert(alert);
+            *pending_alert.lock().unwrap() = Some(responder);
+        });
 
         println!("connect: connected to {}", device.name);
 
         self.data.borrow_mut().device = Some(device);
         self.data.borrow_mut().message_queue = Some(message_queue);
+        self.data.borrow_mut().general_settings.clear();
 
         Ok(false)
     }
@@ -151,7 +237,7 @@ impl Repl {
     where
         T: Iterator<Item = &'a str>,
     {
-        if let Some(_) = words.next() {
+        if words.next().is_some() {
             println!("devices: too many arguments, expected 0");
             return Ok(false);
         }
@@ -165,34 +251,378 @@ impl Repl {
         Ok(false)
     }
 
+    async fn alert<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let word = match words.next() {
+            Some(w) => w,
+            None => {
+                println!("alert: too few arguments, expected 1");
+                return Ok(false);
+            }
+        };
+
+        if words.next().is_some() {
+            println!("alert: too many arguments, expected 1");
+            return Ok(false);
+        }
+
+        let action = match word {
+            "accept" => AlertAction::Positive,
+            "reject" => AlertAction::Negative,
+            "enable" | "disable" => {
+                let value = if word == "enable" {
+                    AlertStatusValue::Enable
+                } else {
+                    AlertStatusValue::Disable
+                };
+                let command =
+                    Command::AlertSetStatus(AlertSetStatus(AlertInquiredType::FixedMessage, value));
+                self.send_message("alert", Message::data_mdr(command)).await;
+                return Ok(false);
+            }
+            w => {
+                println!("alert: {}", ParseError::UnknownArgument(w.to_string()));
+                return Ok(false);
+            }
+        };
+
+        let pending_alert = self.data.borrow().pending_alert.clone();
+        let responder = match pending_alert.lock().unwrap().take() {
+            Some(r) => r,
+            None => {
+                println!("alert: no alert to answer");
+                return Ok(false);
+            }
+        };
+
+        if let Err(e) = responder.respond(action).await {
+            println!("alert: unable to answer alert: {}", e);
+        }
+
+        Ok(false)
+    }
+
     async fn send<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
     where
         T: Iterator<Item = &'a str>,
     {
-        let data = self.data.borrow();
-        let message_queue = match &data.message_queue {
+        match Message::from_repl(words) {
+            Ok(message) => self.send_message("send", message).await,
+            Err(e) => println!("send: {}", e),
+        }
+
+        Ok(false)
+    }
+
+    async fn eq<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let setting = match EqSetting::from_repl(words) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("eq: {}", e);
+                return Ok(false);
+            }
+        };
+
+        if words.next().is_some() {
+            println!("eq: {}", ParseError::UnexpectedArgument);
+            return Ok(false);
+        }
+
+        let command = Command::EqEbbSetParam(EqEbbSetParam(EqEbbInquiredType::PresetEq, setting));
+        self.send_message("eq", Message::data_mdr(command)).await;
+
+        Ok(false)
+    }
+
+    async fn fit_test<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        if words.next().is_some() {
+            println!("fittest: too many arguments, expected 0");
+            return Ok(false);
+        }
+
+        let mut message_queue = match self.data.borrow_mut().message_queue.take() {
             Some(s) => s,
             None => {
-                println!("send: not connected to a device");
+                println!("fittest: not connected to a device");
                 return Ok(false);
             }
         };
 
-        let message = match Message::from_repl(words) {
-            Ok(m) => m,
+        match FitTest::start(&mut message_queue).await {
+            Ok(mut fit_test) => {
+                println!("fittest: measuring, keep the earbuds in");
+                match fit_test.wait().await {
+                    Ok(EarpieceFit(left, right)) => {
+                        println!("fittest: left {:?}, right {:?}", left, right)
+                    }
+                    Err(e) => println!("fittest: {}", e),
+                }
+            }
+            Err(e) => println!("fittest: unable to send message: {}", e),
+        }
+
+        self.data.borrow_mut().message_queue = Some(message_queue);
+
+        Ok(false)
+    }
+
+    /// without arguments, discovers and lists the device's general settings, otherwise sets one of
+    /// them to the value with the given display string or index
+    async fn general_setting<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let inquired_type = match words.next() {
+            None => {
+                self.discover_general_settings().await;
+                return Ok(false);
+            }
+            Some(w) => GsInquiredType::from_repl(&mut std::iter::once(w)),
+        };
+        let inquired_type = match inquired_type {
+            Ok(t) => t,
             Err(e) => {
-                println!("send: {}", e);
+                println!("gs: {}", e);
                 return Ok(false);
             }
         };
 
-        if let Err(e) = message_queue.send(message).await {
-            println!("send: unable to send message: {}", e);
+        // display strings may contain spaces
+        let value = words.collect::<Vec<_>>().join(" ");
+        if value.is_empty() {
+            println!("gs: {}", ParseError::ExpectedArgument);
+            return Ok(false);
+        }
+
+        let param = {
+            let data = self.data.borrow();
+            let capability = match data
+                .general_settings
+                .iter()
+                .find(|(t, _)| *t == inquired_type)
+            {
+                Some((_, c)) => c,
+                None => {
+                    println!(
+                        "gs: {:?} not found, run `gs` without arguments first",
+                        inquired_type
+                    );
+                    return Ok(false);
+                }
+            };
+            match capability.parse_value(&value) {
+                Some(p) => p,
+                None => {
+                    println!(
+                        "gs: expected one of: {}",
+                        capability.value_names().join(", ")
+                    );
+                    return Ok(false);
+                }
+            }
+        };
+
+        let command = Command::GsSetParam(GsSetParam(inquired_type, param));
+        self.send_message("gs", Message::data_mdr(command)).await;
+
+        Ok(false)
+    }
+
+    async fn discover_general_settings(&self) {
+        let mut message_queue = match self.data.borrow_mut().message_queue.take() {
+            Some(s) => s,
+            None => {
+                println!("gs: not connected to a device");
+                return;
+            }
+        };
+
+        match general_settings::discover(&mut message_queue).await {
+            Ok(settings) => {
+                if settings.is_empty() {
+                    println!("gs: the device has no general settings");
+                }
+                for (inquired_type, capability) in &settings {
+                    let GsCapability(GsString(_, subject), GsString(_, summary), _) = capability;
+                    println!(
+                        "{:?}: {} ({}), values: {}",
+                        inquired_type,
+                        subject,
+                        summary,
+                        capability.value_names().join(", ")
+                    );
+                }
+                self.data.borrow_mut().general_settings = settings;
+            }
+            Err(e) => println!("gs: {}", e),
+        }
+
+        self.data.borrow_mut().message_queue = Some(message_queue);
+    }
+
+    async fn playback<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let control = match PlaybackControl::from_repl(words) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("playback: {}", e);
+                return Ok(false);
+            }
+        };
+
+        if words.next().is_some() {
+            println!("playback: {}", ParseError::UnexpectedArgument);
+            return Ok(false);
+        }
+
+        let command =
+            Command::PlaySetStatus(PlaySetStatus(PlayInquiredType::PlaybackController, control));
+        self.send_message("playback", Message::data_mdr(command))
+            .await;
+
+        Ok(false)
+    }
+
+    async fn power_off<'a, T>(&mut self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let force = match words.next() {
+            None => false,
+            Some("force") => true,
+            Some(w) => {
+                println!("poweroff: {}", ParseError::UnknownArgument(w.to_string()));
+                return Ok(false);
+            }
+        };
+
+        if words.next().is_some() {
+            println!("poweroff: too many arguments, expected at most 1");
+            return Ok(false);
+        }
+
+        let name = match &self.data.borrow().device {
+            Some(dev) => dev.name.clone(),
+            None => {
+                println!("poweroff: not connected to a device");
+                return Ok(false);
+            }
+        };
+
+        if !force && !confirm(format!("power off {}? [y/N] ", name)).await? {
+            println!("poweroff: cancelled");
+            return Ok(false);
+        }
+
+        let message_queue = match self.data.borrow_mut().message_queue.take() {
+            Some(s) => s,
+            None => {
+                println!("poweroff: not connected to a device");
+                return Ok(false);
+            }
+        };
+
+        match message_queue.power_off().await {
+            Ok(()) => {
+                // the device closes the connection, so there is nothing left to talk to
+                self.data.borrow_mut().device = None;
+                println!("poweroff: powered off {}", name);
+            }
+            Err(e) => {
+                self.data.borrow_mut().message_queue = Some(message_queue);
+                println!("poweroff: unable to send message: {}", e);
+            }
+        }
+
+        Ok(false)
+    }
+
+    async fn volume<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let volume = match u8::from_repl(words) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("volume: {}", e);
+                return Ok(false);
+            }
+        };
+
+        if words.next().is_some() {
+            println!("volume: {}", ParseError::UnexpectedArgument);
+            return Ok(false);
         }
 
+        let volume = match MusicVolume::new(volume) {
+            Some(v) => v,
+            None => {
+                println!("volume: expected at most {}", MUSIC_VOLUME_MAX);
+                return Ok(false);
+            }
+        };
+
+        let command = Command::PlaySetParam(PlaySetParam(PlayParam::MusicVolume(volume)));
+        self.send_message("volume", Message::data_mdr(command))
+            .await;
+
         Ok(false)
     }
 
+    /// prints how many messages per second are being sent and received
+    fn traffic<'a, T>(&self, words: &mut T) -> Result<ShouldExit>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        if words.next().is_some() {
+            println!("traffic: too many arguments, expected 0");
+            return Ok(false);
+        }
+
+        let rates = match &self.data.borrow().message_queue {
+            Some(q) => q.traffic_rates(),
+            None => {
+                println!("traffic: not connected to a device");
+                return Ok(false);
+            }
+        };
+        println!(
+            "traffic: data {:.1}/s sent, {:.1}/s received; shots {:.1}/s sent, {:.1}/s received",
+            rates.data_sent, rates.data_received, rates.shots_sent, rates.shots_received
+        );
+
+        Ok(false)
+    }
+
+    /// sends `message` to the connected device, printing any errors prefixed with `command_name`
+    async fn send_message(&self, command_name: &str, message: Message) {
+        // take the queue out for the duration of the send so that no `RefCell` borrow is held
+        // across the await
+        let message_queue = match self.data.borrow_mut().message_queue.take() {
+            Some(s) => s,
+            None => {
+                println!("{}: not connected to a device", command_name);
+                return;
+            }
+        };
+
+        if let Err(e) = message_queue.send(message).await {
+            println!("{}: unable to send message: {}", command_name, e);
+        }
+
+        self.data.borrow_mut().message_queue = Some(message_queue);
+    }
+
     async fn quit<'a, T>(&mut self, words: &mut T) -> Result<ShouldExit>
     where
         T: Iterator<Item = &'a str>,
@@ -206,3 +636,58 @@ impl Repl {
         }
     }
 }
+
+/// completes the discovered general settings, followed by the values they can take
+fn general_settings_completion_tree(
+    general_settings: Vec<(GsInquiredType, GsCapability)>,
+) -> CompletionTree {
+    CompletionTree::new(
+        general_settings
+            .into_iter()
+            .map(|(inquired_type, capability)| {
+                let values = capability.value_names();
+                let values_tree = move || {
+                    CompletionTree::new(
+                        values
+                            .into_iter()
+                            .map(|v| (v, CompletionTree::lazy_empty() as _))
+                            .collect(),
+                    )
+                };
+                (
+                    format!("{:?}", inquired_type),
+                    Box::new(values_tree) as Box<dyn FnOnce() -> CompletionTree>,
+                )
+            })
+            .collect(),
+    )
+}
+
+/// tells the user about an alert and how to answer it, since the prompt can't be interrupted to
+/// ask directly
+fn print_alert(alert: AlertNtfyParam) {
+    let AlertNtfyParam(_, message_type, action_type) = alert;
+    match action_type {
+        AlertActionType::PositiveNegative => println!(
+            "alert: {:?}, answer with `alert accept` or `alert reject`",
+            message_type
+        ),
+        AlertActionType::ConfirmationOnly => {
+            println!("alert: {:?}, confirm with `alert accept`", message_type)
+        }
+    }
+}
+
+/// Asks a yes/no question on stdin, off the async runtime since reading blocks
+async fn confirm(prompt: String) -> Result<bool> {
+    tokio::task::spawn_blocking(move || {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    })
+    .await?
+}
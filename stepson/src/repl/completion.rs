@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use macaddr::MacAddr6;
 use rustyline::completion::Completer;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
@@ -25,7 +26,30 @@ impl CompletionTree {
     }
 
     pub fn lazy_empty() -> Box<fn() -> Self> {
-        Box::new(|| Self::empty())
+        Box::new(Self::empty)
+    }
+
+    /// Appends `next` to every leaf of this tree, i.e. completion continues with `next` once this
+    /// tree has been fully traversed. A tree with no branches at all is left as is, since there is
+    /// nothing to complete it with.
+    pub fn then(self, next: fn() -> CompletionTree) -> Self {
+        Self {
+            branches: self
+                .branches
+                .into_iter()
+                .map(|(s, f)| {
+                    let f = move || {
+                        let tree = f();
+                        if tree.branches.is_empty() {
+                            next()
+                        } else {
+                            tree.then(next)
+                        }
+                    };
+                    (s, Box::new(f) as _)
+                })
+                .collect(),
+        }
     }
 
     fn traverse(self, mut words: Vec<String>) -> Vec<String> {
@@ -39,7 +63,7 @@ impl CompletionTree {
             self.branches
                 .into_iter()
                 .filter_map(|(s, _)| {
-                    if s.starts_with(&first_word) {
+                    if s.to_lowercase().starts_with(&first_word.to_lowercase()) {
                         Some(s)
                     } else {
                         None
@@ -47,7 +71,11 @@ impl CompletionTree {
                 })
                 .collect()
         } else {
-            match self.branches.into_iter().find(|(s, _)| s == &first_word) {
+            match self
+                .branches
+                .into_iter()
+                .find(|(s, _)| s.eq_ignore_ascii_case(&first_word))
+            {
                 Some((_, f)) => f().traverse(words),
                 None => vec![],
             }
@@ -73,6 +101,42 @@ impl ReplCompletion for u8 {
     }
 }
 
+impl ReplCompletion for u16 {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl ReplCompletion for u32 {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl ReplCompletion for i8 {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl ReplCompletion for Vec<u8> {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl ReplCompletion for MacAddr6 {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
+impl ReplCompletion for String {
+    fn completion_tree() -> CompletionTree {
+        CompletionTree::empty()
+    }
+}
+
 pub(super) struct ReplHelper {
     pub data: Rc<RefCell<ReplData>>,
 }
@@ -101,7 +165,7 @@ impl Completer for ReplHelper {
                 .collect(),
         );
 
-        let last_word = match words.get(0) {
+        let last_word = match words.first() {
             Some(w) => w.clone(),
             None => String::new(),
         };
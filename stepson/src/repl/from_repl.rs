@@ -2,18 +2,23 @@ pub use derive::FromRepl;
 
 use std::str::FromStr;
 
+use macaddr::MacAddr6;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
+    #[error(transparent)]
+    ParseMacAddr(#[from] macaddr::ParseError),
     #[error("expected argument")]
     ExpectedArgument,
     #[error("unexpected argument")]
     UnexpectedArgument,
     #[error("unknown argument: {0}")]
     UnknownArgument(String),
+    #[error("argument out of range: {0}")]
+    OutOfRange(String),
 }
 
 pub trait FromRepl {
@@ -36,13 +41,75 @@ impl FromRepl for u8 {
     }
 }
 
+impl FromRepl for u16 {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let word = match words.next() {
+            Some(w) => w,
+            None => return Err(ParseError::ExpectedArgument),
+        };
+        Ok(u16::from_str(word)?)
+    }
+}
+
+impl FromRepl for u32 {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let word = match words.next() {
+            Some(w) => w,
+            None => return Err(ParseError::ExpectedArgument),
+        };
+        Ok(u32::from_str(word)?)
+    }
+}
+
+impl FromRepl for i8 {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let word = match words.next() {
+            Some(w) => w,
+            None => return Err(ParseError::ExpectedArgument),
+        };
+        Ok(i8::from_str(word)?)
+    }
+}
+
+impl FromRepl for String {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        match words.next() {
+            Some(w) => Ok(w.to_string()),
+            None => Err(ParseError::ExpectedArgument),
+        }
+    }
+}
+
+impl FromRepl for MacAddr6 {
+    fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let word = match words.next() {
+            Some(w) => w,
+            None => return Err(ParseError::ExpectedArgument),
+        };
+        Ok(MacAddr6::from_str(word)?)
+    }
+}
+
 impl FromRepl for Vec<u8> {
     fn from_repl<'a, T>(words: &mut T) -> Result<Self, ParseError>
     where
         T: Iterator<Item = &'a str>,
     {
-        Ok(words
-            .map(|w| u8::from_str(w))
-            .collect::<Result<Vec<u8>, _>>()?)
+        Ok(words.map(u8::from_str).collect::<Result<Vec<u8>, _>>()?)
     }
 }
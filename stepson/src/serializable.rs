@@ -10,7 +10,7 @@ pub enum DeserializeError {
     #[error("invalid checksum: {0}")]
     InvalidChecksum(u8),
     #[error("invalid length: {0}")]
-    InvalidLength(u8),
+    InvalidLength(usize),
     #[error("invalid escape: {0}")]
     InvalidEscape(u8),
     #[error("invalid start of message: {0}")]
@@ -19,6 +19,8 @@ pub enum DeserializeError {
     InvalidEndOfMessage(u8),
     #[error("unrecognized value: {0}")]
     TryFromPrimitive(u8),
+    #[error("invalid MAC address")]
+    InvalidMacAddress,
 }
 
 impl<T: TryFromPrimitive<Primitive = u8>> From<TryFromPrimitiveError<T>> for DeserializeError {
@@ -27,10 +29,39 @@ impl<T: TryFromPrimitive<Primitive = u8>> From<TryFromPrimitiveError<T>> for Des
     }
 }
 
+/// The MDR protocol version a device reports in `ConnectRetProtocolInfo`, e.g. `0x0200` for 2.0.
+/// Until it is known, devices are assumed to speak `V1`, which is the default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtocolVersion(pub u32);
+
+impl ProtocolVersion {
+    pub const V1: ProtocolVersion = ProtocolVersion(0x0100);
+    pub const V2: ProtocolVersion = ProtocolVersion(0x0200);
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        ProtocolVersion::V1
+    }
+}
+
 //TODO derive macro for simple structs
 pub trait Serializable {
     fn serialize(&self) -> Vec<u8>;
     fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError>
     where
         Self: Sized;
+
+    /// Serializes for a device speaking `version`. Only types whose layout differs between
+    /// versions, and the types containing them, override this.
+    fn serialize_for(&self, _version: ProtocolVersion) -> Vec<u8> {
+        self.serialize()
+    }
+
+    fn deserialize_for(bytes: &[u8], _version: ProtocolVersion) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        Self::deserialize(bytes)
+    }
 }
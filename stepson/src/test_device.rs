@@ -0,0 +1,189 @@
+//! An emulated device on the other end of a `MessageQueue`, for tests
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::io::{ReadHalf, WriteHalf};
+use tokio::sync::mpsc;
+
+use crate::message::ack::Ack;
+use crate::message::data_mdr::{Command, DataMdr};
+use crate::message::{Data, Message, MESSAGE_END, MESSAGE_START};
+use crate::message_queue::MessageQueue;
+use crate::serializable::Serializable;
+
+enum Control {
+    Send(Message),
+    SendRaw(Vec<u8>),
+    Disconnect,
+}
+
+/// Controls the emulated device from the test
+pub struct TestDevice {
+    control_sender: mpsc::UnboundedSender<Control>,
+    received: Arc<Mutex<Vec<Command>>>,
+}
+
+impl TestDevice {
+    /// Sends `message` to the host, as if the device sent it on its own
+    pub fn send(&self, message: Message) {
+        let _ = self.control_sender.send(Control::Send(message));
+    }
+
+    /// Sends `bytes` as they are, e.g. a broken frame
+    pub fn send_raw(&self, bytes: Vec<u8>) {
+        let _ = self.control_sender.send(Control::SendRaw(bytes));
+    }
+
+    /// Closes the connection
+    pub fn disconnect(&self) {
+        let _ = self.control_sender.send(Control::Disconnect);
+    }
+
+    /// The `DataMdr` commands the host has sent so far
+    pub fn received(&self) -> std::sync::MutexGuard<'_, Vec<Command>> {
+        self.received.lock().unwrap()
+    }
+}
+
+/// Connects a queue to a device that acknowledges every frame needing an ack, and answers each
+/// `DataMdr` command with whatever `handler` returns. Time is paused, so timeouts pass as soon as
+/// nothing else is left to do.
+pub fn connect<F>(handler: F) -> (MessageQueue, TestDevice)
+where
+    F: FnMut(&Command) -> Vec<Message> + Send + 'static,
+{
+    tokio::time::pause();
+    let (host, device) = tokio::io::duplex(1 << 16);
+    let (control_sender, control_receiver) = mpsc::unbounded_channel();
+    let received = Arc::new(Mutex::new(vec![]));
+
+    tokio::spawn(run(device, handler, control_receiver, received.clone()));
+
+    (
+        MessageQueue::new(host),
+        TestDevice {
+            control_sender,
+            received,
+        },
+    )
+}
+
+/// The device end of a connection that is driven by hand, e.g. to hold back or repeat acks
+pub struct RawDevice {
+    read: BufReader<ReadHalf<DuplexStream>>,
+    write: WriteHalf<DuplexStream>,
+}
+
+impl RawDevice {
+    /// Waits for the next frame the host writes
+    pub async fn read(&mut self) -> Message {
+        read_message(&mut self.read)
+            .await
+            .expect("the host closed the connection")
+    }
+
+    pub async fn write(&mut self, message: &Message) {
+        write_message(&mut self.write, message).await;
+    }
+
+    /// Acknowledges a frame the host sent with `sequence_number`
+    pub async fn ack(&mut self, sequence_number: u8) {
+        let ack = Message {
+            sequence_number: sequence_number ^ 1,
+            data: Data::Ack(Ack {}),
+        };
+        self.write(&ack).await;
+    }
+}
+
+/// Connects a queue to a `RawDevice`, which doesn't do anything on its own. Time is paused as for
+/// `connect`.
+pub fn connect_raw() -> (MessageQueue, RawDevice) {
+    tokio::time::pause();
+    let (host, device) = tokio::io::duplex(1 << 16);
+    let (read, write) = tokio::io::split(device);
+    (
+        MessageQueue::new(host),
+        RawDevice {
+            read: BufReader::new(read),
+            write,
+        },
+    )
+}
+
+async fn run<F>(
+    stream: DuplexStream,
+    mut handler: F,
+    mut control_receiver: mpsc::UnboundedReceiver<Control>,
+    received: Arc<Mutex<Vec<Command>>>,
+) where
+    F: FnMut(&Command) -> Vec<Message>,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    // frames are read separately, since a read interrupted by `select!` would lose its bytes
+    let (frame_sender, mut frame_receiver) = mpsc::unbounded_channel();
+    let reader = tokio::spawn(async move {
+        let mut read = BufReader::new(read);
+        while let Some(message) = read_message(&mut read).await {
+            if frame_sender.send(message).is_err() {
+                return;
+            }
+        }
+    });
+    // once the test drops its `TestDevice`, the device keeps answering on its own
+    let mut controlled = true;
+
+    loop {
+        tokio::select! {
+            message = frame_receiver.recv() => {
+                let message = match message {
+                    Some(m) => m,
+                    None => return,
+                };
+                if message.requires_ack() {
+                    let ack = Message {
+                        sequence_number: message.sequence_number ^ 1,
+                        data: Data::Ack(Ack {}),
+                    };
+                    write_message(&mut write, &ack).await;
+                }
+                if let Data::DataMdr(DataMdr { command }) = message.data {
+                    let replies = handler(&command);
+                    received.lock().unwrap().push(command);
+                    for reply in replies {
+                        write_message(&mut write, &reply).await;
+                    }
+                }
+            }
+            control = control_receiver.recv(), if controlled => match control {
+                Some(Control::Send(message)) => write_message(&mut write, &message).await,
+                Some(Control::SendRaw(bytes)) => {
+                    let _ = write.write_all(&bytes).await;
+                }
+                Some(Control::Disconnect) => {
+                    // the stream only closes once both halves are gone
+                    reader.abort();
+                    return;
+                }
+                None => controlled = false,
+            },
+        }
+    }
+}
+
+async fn read_message(read: &mut BufReader<ReadHalf<DuplexStream>>) -> Option<Message> {
+    loop {
+        if read.read_u8().await.ok()? == MESSAGE_START {
+            break;
+        }
+    }
+    let mut buf = vec![MESSAGE_START];
+    read.read_until(MESSAGE_END, &mut buf).await.ok()?;
+    Some(Message::deserialize(&buf).unwrap())
+}
+
+async fn write_message(write: &mut WriteHalf<DuplexStream>, message: &Message) {
+    // the host may already be gone, which the test notices on its side
+    let _ = write.write_all(&message.serialize()).await;
+}